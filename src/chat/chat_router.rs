@@ -1,29 +1,69 @@
 use axum::{
+    extract::DefaultBodyLimit,
     middleware::from_fn_with_state,
     routing::{delete, get, patch, post, put},
     Router,
 };
 use tower::ServiceBuilder;
 
-use crate::{shared::arc_clients::State, users::middlewares::is_authenticated::is_authed};
+use crate::{
+    shared::{arc_clients::State, attachment_limits::ATTACHMENT_LIMITS},
+    users::middlewares::is_authenticated::is_authed,
+};
 
 use super::{
+    direct::{
+        add_member::add_member, create_group::create_group, list_direct::list_direct,
+        send_direct_message::send_direct_message,
+    },
     invites::{
-        invite_user::invite_user, response_invite::invite_response,
-        retrieve_invite::retrieve_invite,
+        cancel_invite::cancel_invite, create_invite_link::create_invite_link,
+        invite_user::invite_user, redeem_invite_link::redeem_invite_link,
+        response_invite::invite_response, retrieve_invite::retrieve_invite,
+        retrieve_invite_links::retrieve_invite_links, revoke_invite_link::revoke_invite_link,
     },
     messages::{
-        delete_message::delete_message, middlewares::can_talk::can_talk,
-        retrieve_message::retrieve_message, retrieve_messages::retrieve_messages,
+        cancel_scheduled::cancel_scheduled,
+        delete_message::delete_message,
+        edit_message::edit_message,
+        edit_scheduled::edit_scheduled,
+        middlewares::can_talk::{can_edit, can_talk},
+        pin_message::{pin_message, unpin_message},
+        react_message::{add_reaction, remove_reaction},
+        read_messages::read_messages,
+        retrieve_attachment::retrieve_attachment,
+        retrieve_message::retrieve_message,
+        retrieve_messages::{retrieve_history, retrieve_messages},
+        retrieve_pinned::retrieve_pinned,
+        retrieve_revisions::retrieve_revisions,
+        retrieve_scheduled::retrieve_scheduled,
+        retrieve_thread::retrieve_thread,
+        schedule_message::schedule_message,
+        search_messages::{search_all_messages, search_messages},
         send_message::send_message,
+        upload_attachment::upload_attachment,
     },
     middlewares::{is_owner::is_owner, is_participant::is_participant},
+    requests::{
+        resolve_request::resolve_request, retrieve_requests::retrieve_requests,
+        submit_request::submit_request,
+    },
     rooms::{
         create_chat::create_chat,
         join_chat::join_chat,
         leave_chat::leave_chat,
-        moderation::{ban_user::ban_user, unban_user::unban_user},
+        list_chats::list_chats,
+        moderation::{
+            archive_chat::{archive_chat, unarchive_chat},
+            audit_log::retrieve_audit_log,
+            ban_user::ban_user,
+            kick_user::kick_user,
+            retrieve_bans::retrieve_bans,
+            room_settings::update_room_settings,
+            unban_user::unban_user,
+        },
         retrieve_chat::retrieve_chat,
+        retrieve_unread::retrieve_unread,
     },
 };
 
@@ -34,14 +74,26 @@ pub fn chat_general_router(state: State) -> Router {
 pub fn chatroom_router(state: State) -> Router {
     Router::new()
         .route("/", post(create_chat))
+        .route("/", get(list_chats))
+        .route("/unread", get(retrieve_unread))
+        .route("/search", get(search_all_messages))
         .route("/chat-:id", get(retrieve_chat))
         .route("/chat-:id", patch(join_chat))
         .route("/chat-:id", delete(leave_chat))
+        .route("/links/:code", post(redeem_invite_link))
+        .route("/direct", get(list_direct))
+        .route("/direct/group", post(create_group))
+        .route("/direct/:user_id", post(send_direct_message))
+        .route(
+            "/chat-:id/members/:user_id",
+            put(add_member).layer(from_fn_with_state(state.clone(), is_participant)),
+        )
         .layer(from_fn_with_state(state.clone(), is_authed))
         .with_state(state.clone())
         .nest("/chat-:id/messages", messages_router(state.clone()))
         .nest("/chat-:id/moderation", moderation_router(state.clone()))
-        .nest("/chat-:id/invites", invites_router(state))
+        .nest("/chat-:id/invites", invites_router(state.clone()))
+        .nest("/chat-:id/requests", requests_router(state))
 }
 
 pub fn messages_router(state: State) -> Router {
@@ -52,7 +104,49 @@ pub fn messages_router(state: State) -> Router {
             "/",
             post(send_message).layer(from_fn_with_state(state.clone(), can_talk)),
         )
+        .route("/", get(retrieve_history))
         .route("/:message_id", delete(delete_message))
+        .route(
+            "/:message_id",
+            patch(edit_message).layer(from_fn_with_state(state.clone(), can_edit)),
+        )
+        .route(
+            "/revisions/:message_id",
+            get(retrieve_revisions).layer(from_fn_with_state(state.clone(), is_owner)),
+        )
+        .route("/pinned", get(retrieve_pinned))
+        .route("/read", put(read_messages))
+        .route("/search", get(search_messages))
+        .route(
+            "/attachments",
+            post(upload_attachment).layer(
+                ServiceBuilder::new()
+                    // Room for the multipart framing around the file itself
+                    .layer(DefaultBodyLimit::max(
+                        ATTACHMENT_LIMITS.max_size + 64 * 1024,
+                    ))
+                    .layer(from_fn_with_state(state.clone(), can_edit)),
+            ),
+        )
+        .route("/attachments/:attachment_id", get(retrieve_attachment))
+        .route(
+            "/scheduled",
+            post(schedule_message).layer(from_fn_with_state(state.clone(), can_talk)),
+        )
+        .route("/scheduled", get(retrieve_scheduled))
+        .route("/scheduled/:scheduled_id", patch(edit_scheduled))
+        .route("/scheduled/:scheduled_id", delete(cancel_scheduled))
+        .route("/thread/:message_id", get(retrieve_thread))
+        .route("/reactions/:message_id/:emoji", put(add_reaction))
+        .route("/reactions/:message_id/:emoji", delete(remove_reaction))
+        .route(
+            "/pinned/:message_id",
+            put(pin_message).layer(from_fn_with_state(state.clone(), is_owner)),
+        )
+        .route(
+            "/pinned/:message_id",
+            delete(unpin_message).layer(from_fn_with_state(state.clone(), is_owner)),
+        )
         .layer(
             ServiceBuilder::new()
                 .layer(from_fn_with_state(state.clone(), is_authed))
@@ -63,8 +157,14 @@ pub fn messages_router(state: State) -> Router {
 
 pub fn moderation_router(state: State) -> Router {
     Router::new()
+        .route("/bans", get(retrieve_bans))
+        .route("/log", get(retrieve_audit_log))
+        .route("/settings", patch(update_room_settings))
+        .route("/archive", put(archive_chat))
+        .route("/archive", delete(unarchive_chat))
         .route("/:user_id", post(ban_user))
         .route("/:user_id", delete(unban_user))
+        .route("/:user_id/kick", post(kick_user))
         .layer(
             ServiceBuilder::new()
                 .layer(from_fn_with_state(state.clone(), is_authed))
@@ -78,10 +178,50 @@ pub fn invites_router(state: State) -> Router {
     Router::new()
         .route("/:invite_id", get(retrieve_invite))
         .route("/:invite_id", put(invite_response))
+        .route("/:invite_id", delete(cancel_invite))
         .route(
             "/invite/:user_id",
             post(invite_user).layer(from_fn_with_state(state.clone(), is_participant)),
         )
+        .route(
+            "/links",
+            post(create_invite_link).layer(from_fn_with_state(state.clone(), is_participant)),
+        )
+        .route(
+            "/links",
+            get(retrieve_invite_links).layer(
+                ServiceBuilder::new()
+                    .layer(from_fn_with_state(state.clone(), is_participant))
+                    .layer(from_fn_with_state(state.clone(), is_owner)),
+            ),
+        )
+        .route(
+            "/links/:code",
+            delete(revoke_invite_link).layer(from_fn_with_state(state.clone(), is_participant)),
+        )
+        .layer(ServiceBuilder::new().layer(from_fn_with_state(state.clone(), is_authed)))
+        .with_state(state)
+}
+
+pub fn requests_router(state: State) -> Router {
+    Router::new()
+        .route("/", post(submit_request))
+        .route(
+            "/",
+            get(retrieve_requests).layer(
+                ServiceBuilder::new()
+                    .layer(from_fn_with_state(state.clone(), is_participant))
+                    .layer(from_fn_with_state(state.clone(), is_owner)),
+            ),
+        )
+        .route(
+            "/:request_id",
+            put(resolve_request).layer(
+                ServiceBuilder::new()
+                    .layer(from_fn_with_state(state.clone(), is_participant))
+                    .layer(from_fn_with_state(state.clone(), is_owner)),
+            ),
+        )
         .layer(ServiceBuilder::new().layer(from_fn_with_state(state.clone(), is_authed)))
         .with_state(state)
 }
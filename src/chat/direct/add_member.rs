@@ -0,0 +1,198 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use rustis::commands::PubSubCommands;
+use serde::Serialize;
+
+use crate::{
+    chat::interfaces::single_user_param::SingleUserParam,
+    prisma_client::client::{rooms, user, users_rooms, RoomKind},
+    rejection::path::CustomPathDataRejection,
+    shared::{arc_clients::State as AppState, room_limits::ROOM_LIMITS},
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+    users::handlers::block_user::is_blocked,
+};
+
+#[derive(Serialize)]
+pub struct AddMemberErrorResponse {
+    pub success: bool,
+    pub http_code: u16,
+    pub error: String,
+}
+
+pub async fn add_member(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(SingleUserParam { user_id }), _): WithRejection<
+        Path<SingleUserParam>,
+        CustomPathDataRejection,
+    >,
+) -> Result<StatusCode, (StatusCode, Json<AddMemberErrorResponse>)> {
+    if participant.room.as_ref().unwrap().kind != RoomKind::GroupDirect {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(AddMemberErrorResponse {
+                success: false,
+                http_code: 400,
+                error: "Members can only be added to group conversations".to_string(),
+            }),
+        ));
+    }
+    if user_id > i32::MAX as u32 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(AddMemberErrorResponse {
+                success: false,
+                http_code: 400,
+                error: "User id cannot exceed 32 bits signed".to_string(),
+            }),
+        ));
+    }
+    let user_id = user_id as i32;
+    let member = state
+        .prisma_client
+        .user()
+        .find_unique(user::UniqueWhereParam::IdEquals(user_id))
+        .exec()
+        .await;
+    let member = match member {
+        Ok(Some(member)) if !member.banned => member,
+        Ok(_) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(AddMemberErrorResponse {
+                    success: false,
+                    http_code: 404,
+                    error: "User not found".to_string(),
+                }),
+            ))
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AddMemberErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ))
+        }
+    };
+    match is_blocked(state.prisma_client.clone(), participant.user_id, member.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(AddMemberErrorResponse {
+                    success: false,
+                    http_code: 403,
+                    error: "You can't add this user".to_string(),
+                }),
+            ))
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AddMemberErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ))
+        }
+    };
+    let members = state
+        .prisma_client
+        .users_rooms()
+        .find_many(vec![users_rooms::room_id::equals(participant.room_id)])
+        .exec()
+        .await;
+    let members = match members {
+        Ok(members) => members,
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AddMemberErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ))
+        }
+    };
+    if members.iter().any(|m| m.user_id == member.id) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(AddMemberErrorResponse {
+                success: false,
+                http_code: 409,
+                error: "User is already a member".to_string(),
+            }),
+        ));
+    }
+    if members.len() as i64 >= ROOM_LIMITS.max_group_members {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(AddMemberErrorResponse {
+                success: false,
+                http_code: 400,
+                error: "Group is full".to_string(),
+            }),
+        ));
+    }
+    let participant_insertion = state
+        .prisma_client
+        .users_rooms()
+        .create(
+            user::UniqueWhereParam::IdEquals(member.id),
+            rooms::UniqueWhereParam::IdEquals(participant.room_id),
+            vec![],
+        )
+        .exec()
+        .await;
+    if participant_insertion.is_err() {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(AddMemberErrorResponse {
+                success: false,
+                http_code: 500,
+                error: "Internal server error".to_string(),
+            }),
+        ));
+    }
+    state
+        .redis_client
+        .publish(
+            format!("priv_user:{}", member.id),
+            serde_json::to_string(&WebSocketMessage {
+                record: Records::Message,
+                queue: format!("chat-{}", participant.room_id),
+                data: serde_json::json!({
+                    "chat_id": participant.room_id,
+                    "type": "group",
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+    state
+        .redis_client
+        .publish(
+            format!("chat:{}", participant.room_id),
+            serde_json::to_string(&WebSocketMessage {
+                record: Records::ParticipantJoined,
+                queue: format!("chat:{}", participant.room_id),
+                data: serde_json::json!({
+                    "user_id": member.id,
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+    Ok(StatusCode::NO_CONTENT)
+}
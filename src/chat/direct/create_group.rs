@@ -0,0 +1,303 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Json as ExtractJson, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use rustis::commands::PubSubCommands;
+use rustrict::CensorStr;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{rooms, user, JoinPolicy, RoomKind},
+    rejection::json::CustomJsonDataRejection,
+    shared::{arc_clients::State as AppState, room_limits::ROOM_LIMITS},
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+    users::handlers::block_user::is_blocked,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct CreateGroupBody {
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "name must be between 1 and 50 characters"
+    ))]
+    pub name: Option<String>,
+    #[validate(
+        required(message = "user_ids is required"),
+        length(min = 1, message = "user_ids must contain at least one user")
+    )]
+    pub user_ids: Option<Vec<u32>>,
+}
+
+#[derive(Serialize)]
+pub struct CreateGroupResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn create_group(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<CreateGroupBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<CreateGroupResponse>) {
+    match body.validate() {
+        Ok(_) => {
+            let name = body.name.unwrap_or_else(|| "group".to_string());
+            if name.is_inappropriate() {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(CreateGroupResponse {
+                        success: false,
+                        http_code: 400,
+                        chat_id: None,
+                        validation_errors: None,
+                        error: Some("Name is inappropriate".to_string()),
+                    }),
+                );
+            }
+            if user.banned {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(CreateGroupResponse {
+                        success: false,
+                        http_code: 403,
+                        chat_id: None,
+                        validation_errors: None,
+                        error: Some("You are banned".to_string()),
+                    }),
+                );
+            }
+            let mut member_ids = HashSet::new();
+            for user_id in body.user_ids.unwrap() {
+                if user_id > i32::MAX as u32 {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        Json(CreateGroupResponse {
+                            success: false,
+                            http_code: 400,
+                            chat_id: None,
+                            validation_errors: None,
+                            error: Some("User id cannot exceed 32 bits signed".to_string()),
+                        }),
+                    );
+                }
+                if user_id as i32 != user.id {
+                    member_ids.insert(user_id as i32);
+                }
+            }
+            if member_ids.is_empty() {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(CreateGroupResponse {
+                        success: false,
+                        http_code: 400,
+                        chat_id: None,
+                        validation_errors: None,
+                        error: Some("You can't start a group with yourself".to_string()),
+                    }),
+                );
+            }
+            // The creator takes a seat as well
+            if member_ids.len() as i64 + 1 > ROOM_LIMITS.max_group_members {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(CreateGroupResponse {
+                        success: false,
+                        http_code: 400,
+                        chat_id: None,
+                        validation_errors: None,
+                        error: Some("Too many members for a group".to_string()),
+                    }),
+                );
+            }
+            let members = state
+                .prisma_client
+                .user()
+                .find_many(vec![
+                    user::id::in_vec(member_ids.iter().cloned().collect()),
+                    user::banned::equals(false),
+                ])
+                .exec()
+                .await;
+            let members = match members {
+                Ok(members) => members,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(CreateGroupResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            if members.len() != member_ids.len() {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(CreateGroupResponse {
+                        success: false,
+                        http_code: 404,
+                        chat_id: None,
+                        validation_errors: None,
+                        error: Some("User not found".to_string()),
+                    }),
+                );
+            }
+            for member in members.iter() {
+                match is_blocked(state.prisma_client.clone(), user.id, member.id).await {
+                    Ok(false) => {}
+                    Ok(true) => {
+                        return (
+                            StatusCode::FORBIDDEN,
+                            Json(CreateGroupResponse {
+                                success: false,
+                                http_code: 403,
+                                chat_id: None,
+                                validation_errors: None,
+                                error: Some("You can't add this user".to_string()),
+                            }),
+                        )
+                    }
+                    Err(_) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(CreateGroupResponse {
+                                success: false,
+                                http_code: 500,
+                                chat_id: None,
+                                validation_errors: None,
+                                error: Some("Internal server error".to_string()),
+                            }),
+                        )
+                    }
+                };
+            }
+            let room = state
+                .prisma_client
+                .rooms()
+                .create(
+                    name,
+                    ROOM_LIMITS.max_group_members as i32,
+                    vec![
+                        rooms::kind::set(RoomKind::GroupDirect),
+                        rooms::join_policy::set(JoinPolicy::Invite),
+                    ],
+                )
+                .exec()
+                .await;
+            let room = match room {
+                Ok(room) => room,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(CreateGroupResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let participant_ids = std::iter::once(user.id).chain(members.iter().map(|m| m.id));
+            for participant_id in participant_ids {
+                let participant_insertion = state
+                    .prisma_client
+                    .users_rooms()
+                    .create(
+                        user::UniqueWhereParam::IdEquals(participant_id),
+                        rooms::UniqueWhereParam::IdEquals(room.id),
+                        vec![],
+                    )
+                    .exec()
+                    .await;
+                if participant_insertion.is_err() {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(CreateGroupResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    );
+                }
+                state
+                    .redis_client
+                    .publish(
+                        format!("priv_user:{}", participant_id),
+                        serde_json::to_string(&WebSocketMessage {
+                            record: Records::Message,
+                            queue: format!("chat-{}", room.id),
+                            data: serde_json::json!({
+                                "chat_id": room.id,
+                                "type": "group",
+                            }),
+                        })
+                        .unwrap(),
+                    )
+                    .await
+                    .ok();
+            }
+            (
+                StatusCode::CREATED,
+                Json(CreateGroupResponse {
+                    success: true,
+                    http_code: 201,
+                    chat_id: Some(room.id),
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(CreateGroupResponse {
+                    success: false,
+                    http_code: 422,
+                    chat_id: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::Serialize;
+use validator::Validate;
+
+use crate::{
+    chat::{interfaces::pagination_query::PaginationQuery, messages::retrieve_messages::Sender},
+    prisma_client::client::{rooms, user, users_rooms, RoomKind},
+    rejection::query::CustomQueryDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Serialize)]
+pub struct DirectConversation {
+    pub id: i32,
+    pub kind: RoomKind,
+    pub name: String,
+    pub peers: Vec<Sender>,
+}
+
+#[derive(Serialize)]
+pub struct ListDirectResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversations: Option<Vec<DirectConversation>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn list_direct(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Query(pagination), _): WithRejection<
+        Query<PaginationQuery>,
+        CustomQueryDataRejection,
+    >,
+) -> (StatusCode, Json<ListDirectResponse>) {
+    if pagination.validate().is_err() {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ListDirectResponse {
+                success: false,
+                http_code: 422,
+                conversations: None,
+                error: Some("page must be greater than 0 and limit between 1 and 50".to_string()),
+            }),
+        );
+    }
+    let participations = state
+        .prisma_client
+        .users_rooms()
+        .find_many(vec![
+            users_rooms::user_id::equals(user.id),
+            users_rooms::room::is(vec![rooms::kind::in_vec(vec![
+                RoomKind::Direct,
+                RoomKind::GroupDirect,
+            ])]),
+        ])
+        .with(
+            users_rooms::room::fetch().with(
+                rooms::users_rooms::fetch(vec![users_rooms::user_id::not(user.id)])
+                    .with(users_rooms::user::fetch()),
+            ),
+        )
+        .order_by(users_rooms::id::order(prisma_client_rust::Direction::Desc))
+        .skip(pagination.skip())
+        .take(pagination.take())
+        .exec()
+        .await;
+    match participations {
+        Ok(participations) => {
+            let conversations = participations
+                .into_iter()
+                .map(|participation| {
+                    let room = participation.room.unwrap();
+                    let peers = room
+                        .users_rooms
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|peer| peer.user)
+                        .map(|peer| Sender {
+                            id: peer.id,
+                            username: peer.username,
+                        })
+                        .collect();
+                    DirectConversation {
+                        id: room.id,
+                        kind: room.kind,
+                        name: room.name,
+                        peers,
+                    }
+                })
+                .collect();
+            (
+                StatusCode::OK,
+                Json(ListDirectResponse {
+                    success: true,
+                    http_code: 200,
+                    conversations: Some(conversations),
+                    error: None,
+                }),
+            )
+        }
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ListDirectResponse {
+                success: false,
+                http_code: 500,
+                conversations: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        ),
+    }
+}
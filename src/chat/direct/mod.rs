@@ -0,0 +1,4 @@
+pub mod add_member;
+pub mod create_group;
+pub mod list_direct;
+pub mod send_direct_message;
@@ -0,0 +1,474 @@
+use axum::{
+    extract::{Json as ExtractJson, Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use rustis::commands::PubSubCommands;
+use rustrict::CensorStr;
+use serde::Serialize;
+use validator::Validate;
+
+use crate::{
+    chat::{
+        interfaces::single_user_param::SingleUserParam,
+        messages::{
+            markdown::format_message,
+            middlewares::can_talk::check_ratelimit,
+            send_message::{publish_message, SendMessageBody},
+        },
+    },
+    error::validation_error::ValidationError,
+    prisma_client::client::{
+        messages, rooms, user, users_rooms, JoinPolicy, MessageFormat, RoomKind,
+    },
+    rejection::{json::CustomJsonDataRejection, path::CustomPathDataRejection},
+    shared::arc_clients::State as AppState,
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+    users::handlers::block_user::is_blocked,
+};
+
+#[derive(Serialize)]
+pub struct SendDirectMessageResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Both orders of a pair map onto the same key
+pub fn direct_key(user_id: i32, other_id: i32) -> String {
+    format!("{}:{}", user_id.min(other_id), user_id.max(other_id))
+}
+
+pub async fn send_direct_message(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Path(SingleUserParam { user_id }), _): WithRejection<
+        Path<SingleUserParam>,
+        CustomPathDataRejection,
+    >,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<SendMessageBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<SendDirectMessageResponse>) {
+    match body.validate() {
+        Ok(_) => {
+            // Direct conversations have no threads or uploads
+            if body.parent_id.is_some() || body.attachment_ids.is_some() {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(SendDirectMessageResponse {
+                        success: false,
+                        http_code: 400,
+                        chat_id: None,
+                        message: None,
+                        validation_errors: None,
+                        error: Some(
+                            "Direct messages can't reply in threads or carry attachments"
+                                .to_string(),
+                        ),
+                    }),
+                );
+            }
+            let message = body.message.unwrap();
+            let format = body.format.unwrap_or(MessageFormat::Plain);
+            let formatted = format_message(&message, format);
+            if formatted.visible_text.is_inappropriate() {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(SendDirectMessageResponse {
+                        success: false,
+                        http_code: 400,
+                        chat_id: None,
+                        message: None,
+                        validation_errors: None,
+                        error: Some("Message is inappropriate".to_string()),
+                    }),
+                );
+            }
+            if user_id > i32::MAX as u32 {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(SendDirectMessageResponse {
+                        success: false,
+                        http_code: 400,
+                        chat_id: None,
+                        message: None,
+                        validation_errors: None,
+                        error: Some("User id cannot exceed 32 bits signed".to_string()),
+                    }),
+                );
+            }
+            let user_id = user_id as i32;
+            if user_id == user.id {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(SendDirectMessageResponse {
+                        success: false,
+                        http_code: 400,
+                        chat_id: None,
+                        message: None,
+                        validation_errors: None,
+                        error: Some("You can't message yourself".to_string()),
+                    }),
+                );
+            }
+            if user.banned {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(SendDirectMessageResponse {
+                        success: false,
+                        http_code: 403,
+                        chat_id: None,
+                        message: None,
+                        validation_errors: None,
+                        error: Some("You are banned".to_string()),
+                    }),
+                );
+            }
+            let recipient = state
+                .prisma_client
+                .user()
+                .find_unique(user::UniqueWhereParam::IdEquals(user_id))
+                .exec()
+                .await;
+            let recipient = match recipient {
+                Ok(Some(recipient)) => recipient,
+                Ok(None) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(SendDirectMessageResponse {
+                            success: false,
+                            http_code: 404,
+                            chat_id: None,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("User not found".to_string()),
+                        }),
+                    )
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(SendDirectMessageResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            if recipient.banned {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(SendDirectMessageResponse {
+                        success: false,
+                        http_code: 404,
+                        chat_id: None,
+                        message: None,
+                        validation_errors: None,
+                        error: Some("User not found".to_string()),
+                    }),
+                );
+            }
+            match is_blocked(state.prisma_client.clone(), user.id, recipient.id).await {
+                Ok(false) => {}
+                Ok(true) => {
+                    return (
+                        StatusCode::FORBIDDEN,
+                        Json(SendDirectMessageResponse {
+                            success: false,
+                            http_code: 403,
+                            chat_id: None,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("You can't message this user".to_string()),
+                        }),
+                    )
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(SendDirectMessageResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            // The pair key is unique so concurrent first messages end up in the same room
+            let key = direct_key(user.id, recipient.id);
+            // Rate limited before anything is written, a throttled sender can't create rooms
+            // or notify anyone
+            let existing_room = state
+                .prisma_client
+                .rooms()
+                .find_unique(rooms::UniqueWhereParam::DirectKeyEquals(key.clone()))
+                .exec()
+                .await;
+            let existing_room = match existing_room {
+                Ok(existing_room) => existing_room,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(SendDirectMessageResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            if let Some(existing_room) = &existing_room {
+                let rate_limit = check_ratelimit(
+                    state.redis_client.clone(),
+                    user.id.into(),
+                    existing_room.id.into(),
+                    existing_room.message_limit.into(),
+                    existing_room.message_window.into(),
+                )
+                .await;
+                match rate_limit {
+                    Ok(None) => {}
+                    Ok(Some(_)) => {
+                        return (
+                            StatusCode::TOO_MANY_REQUESTS,
+                            Json(SendDirectMessageResponse {
+                                success: false,
+                                http_code: 429,
+                                chat_id: None,
+                                message: None,
+                                validation_errors: None,
+                                error: Some("Sending Messages Too Fast".to_string()),
+                            }),
+                        )
+                    }
+                    Err(_) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(SendDirectMessageResponse {
+                                success: false,
+                                http_code: 500,
+                                chat_id: None,
+                                message: None,
+                                validation_errors: None,
+                                error: Some("Internal server error".to_string()),
+                            }),
+                        )
+                    }
+                };
+            }
+            let room = state
+                .prisma_client
+                .rooms()
+                .upsert(
+                    rooms::UniqueWhereParam::DirectKeyEquals(key.clone()),
+                    (
+                        "direct".to_string(),
+                        2,
+                        vec![
+                            rooms::kind::set(RoomKind::Direct),
+                            rooms::direct_key::set(Some(key)),
+                            rooms::join_policy::set(JoinPolicy::Invite),
+                        ],
+                    ),
+                    vec![],
+                )
+                .exec()
+                .await;
+            let room = match room {
+                Ok(room) => room,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(SendDirectMessageResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let participants = state
+                .prisma_client
+                .users_rooms()
+                .find_many(vec![users_rooms::room_id::equals(room.id)])
+                .exec()
+                .await;
+            let participants = match participants {
+                Ok(participants) => participants,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(SendDirectMessageResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            for participant_id in [user.id, recipient.id] {
+                if participants
+                    .iter()
+                    .any(|participant| participant.user_id == participant_id)
+                {
+                    continue;
+                }
+                // A concurrent first message may have added them meanwhile, the unique
+                // user and room pair turns that into a no-op
+                let participant_insertion = state
+                    .prisma_client
+                    .users_rooms()
+                    .upsert(
+                        users_rooms::user_id_room_id(participant_id, room.id),
+                        (
+                            user::UniqueWhereParam::IdEquals(participant_id),
+                            rooms::UniqueWhereParam::IdEquals(room.id),
+                            vec![],
+                        ),
+                        vec![],
+                    )
+                    .exec()
+                    .await;
+                if participant_insertion.is_err() {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(SendDirectMessageResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    );
+                }
+                state
+                    .redis_client
+                    .publish(
+                        format!("priv_user:{}", participant_id),
+                        serde_json::to_string(&WebSocketMessage {
+                            record: Records::Message,
+                            queue: format!("chat-{}", room.id),
+                            data: serde_json::json!({
+                                "chat_id": room.id,
+                                "type": "direct",
+                            }),
+                        })
+                        .unwrap(),
+                    )
+                    .await
+                    .ok();
+            }
+            // A new room has no window yet, its first message only starts one
+            if existing_room.is_none() {
+                check_ratelimit(
+                    state.redis_client.clone(),
+                    user.id.into(),
+                    room.id.into(),
+                    room.message_limit.into(),
+                    room.message_window.into(),
+                )
+                .await
+                .ok();
+            }
+            let message = state
+                .prisma_client
+                .messages()
+                .create(
+                    message.to_string(),
+                    user::UniqueWhereParam::IdEquals(user.id),
+                    rooms::UniqueWhereParam::IdEquals(room.id),
+                    vec![
+                        messages::format::set(format),
+                        messages::rendered::set(formatted.rendered),
+                    ],
+                )
+                .exec()
+                .await;
+            let message = match message {
+                Ok(message) => message,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(SendDirectMessageResponse {
+                            success: false,
+                            http_code: 500,
+                            chat_id: None,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("Failed to create message".to_string()),
+                        }),
+                    )
+                }
+            };
+            publish_message(&state, &message).await;
+            (
+                StatusCode::CREATED,
+                Json(SendDirectMessageResponse {
+                    success: true,
+                    http_code: 201,
+                    chat_id: Some(room.id),
+                    message: Some(message.message),
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::BAD_REQUEST,
+                Json(SendDirectMessageResponse {
+                    success: false,
+                    http_code: 400,
+                    chat_id: None,
+                    message: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
@@ -1 +1,2 @@
+pub mod pagination_query;
 pub mod single_user_param;
@@ -0,0 +1,20 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate)]
+pub struct PaginationQuery {
+    #[validate(range(min = 1, message = "page must be greater than 0"))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 50, message = "limit must be between 1 and 50"))]
+    pub limit: Option<i64>,
+}
+
+impl PaginationQuery {
+    pub fn take(&self) -> i64 {
+        self.limit.unwrap_or(25)
+    }
+
+    pub fn skip(&self) -> i64 {
+        (self.page.unwrap_or(1) - 1).saturating_mul(self.take())
+    }
+}
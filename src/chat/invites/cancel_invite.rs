@@ -0,0 +1,120 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{invites, user, InviteState},
+    rejection::path::CustomPathDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+use super::{expire_invites::notify_invite_state, interfaces::invite_id_param::InviteIdParam};
+
+#[derive(Serialize)]
+pub struct CancelInviteResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn cancel_invite(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Path(InviteIdParam { invite_id }), _): WithRejection<
+        Path<InviteIdParam>,
+        CustomPathDataRejection,
+    >,
+) -> (StatusCode, Json<CancelInviteResponse>) {
+    let invite = state
+        .prisma_client
+        .invites()
+        .find_first(vec![
+            invites::id::equals(invite_id),
+            invites::from_id::equals(user.id),
+        ])
+        .exec()
+        .await;
+    let invite = match invite {
+        Ok(Some(invite)) => invite,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(CancelInviteResponse {
+                    success: false,
+                    http_code: 404,
+                    error: Some("Invite not found".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(CancelInviteResponse {
+                    success: false,
+                    http_code: 500,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    if invite.state != InviteState::Pending {
+        return (
+            StatusCode::CONFLICT,
+            Json(CancelInviteResponse {
+                success: false,
+                http_code: 409,
+                error: Some("Invite is no longer pending".to_string()),
+            }),
+        );
+    }
+    // Guard on the state so an invite answered in the meantime is left alone
+    let cancellation = state
+        .prisma_client
+        .invites()
+        .update_many(
+            vec![
+                invites::id::equals(invite.id.clone()),
+                invites::state::equals(InviteState::Pending),
+            ],
+            vec![invites::state::set(InviteState::Revoked)],
+        )
+        .exec()
+        .await;
+    match cancellation {
+        Ok(0) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(CancelInviteResponse {
+                    success: false,
+                    http_code: 409,
+                    error: Some("Invite is no longer pending".to_string()),
+                }),
+            )
+        }
+        Ok(_) => {}
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(CancelInviteResponse {
+                    success: false,
+                    http_code: 500,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    notify_invite_state(state.redis_client.clone(), &invite, InviteState::Revoked).await;
+    (
+        StatusCode::OK,
+        Json(CancelInviteResponse {
+            success: true,
+            http_code: 200,
+            error: None,
+        }),
+    )
+}
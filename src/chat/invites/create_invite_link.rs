@@ -0,0 +1,170 @@
+use axum::{
+    extract::{Json as ExtractJson, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{invite_links, rooms, user, users_rooms, RoomKind},
+    rejection::json::CustomJsonDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct CreateInviteLinkBody {
+    // Minutes until the link expires, leaving it out keeps the link valid until revoked
+    #[validate(range(
+        min = 1,
+        max = 43200,
+        message = "expires_in must be between 1 and 43200 minutes"
+    ))]
+    pub expires_in: Option<i64>,
+    #[validate(range(min = 1, max = 1000, message = "max_uses must be between 1 and 1000"))]
+    pub max_uses: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct InviteLink {
+    pub code: String,
+    pub creator_id: i32,
+    pub uses: i32,
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<invite_links::Data> for InviteLink {
+    fn from(value: invite_links::Data) -> Self {
+        Self {
+            code: value.id,
+            creator_id: value.creator_id,
+            uses: value.uses,
+            max_uses: value.max_uses,
+            expires_at: value.expires_at.map(|expires_at| expires_at.into()),
+            revoked: value.revoked,
+            created_at: value.created_at.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CreateInviteLinkResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<InviteLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn create_invite_link(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<CreateInviteLinkBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<CreateInviteLinkResponse>) {
+    match body.validate() {
+        Ok(_) => {
+            if participant.room.as_ref().unwrap().archived {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(CreateInviteLinkResponse {
+                        success: false,
+                        http_code: 403,
+                        link: None,
+                        validation_errors: None,
+                        error: Some("Chat is archived".to_string()),
+                    }),
+                );
+            }
+            if participant.room.as_ref().unwrap().kind != RoomKind::Group {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(CreateInviteLinkResponse {
+                        success: false,
+                        http_code: 403,
+                        link: None,
+                        validation_errors: None,
+                        error: Some("Direct conversations cannot be shared".to_string()),
+                    }),
+                );
+            }
+            let expires_at = body.expires_in.map(|expires_in| {
+                (chrono::Utc::now() + chrono::Duration::minutes(expires_in)).into()
+            });
+            let link = state
+                .prisma_client
+                .invite_links()
+                .create(
+                    rooms::UniqueWhereParam::IdEquals(participant.room_id),
+                    user::UniqueWhereParam::IdEquals(participant.user_id),
+                    vec![
+                        invite_links::expires_at::set(expires_at),
+                        invite_links::max_uses::set(body.max_uses),
+                    ],
+                )
+                .exec()
+                .await;
+            match link {
+                Ok(link) => (
+                    StatusCode::CREATED,
+                    Json(CreateInviteLinkResponse {
+                        success: true,
+                        http_code: 201,
+                        link: Some(link.into()),
+                        validation_errors: None,
+                        error: None,
+                    }),
+                ),
+                Err(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(CreateInviteLinkResponse {
+                        success: false,
+                        http_code: 500,
+                        link: None,
+                        validation_errors: None,
+                        error: Some("Internal server error".to_string()),
+                    }),
+                ),
+            }
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(CreateInviteLinkResponse {
+                    success: false,
+                    http_code: 422,
+                    link: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
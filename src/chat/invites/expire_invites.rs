@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use rustis::{client::Client, commands::PubSubCommands};
+
+use crate::{
+    prisma_client::client::{invites, InviteState},
+    shared::{arc_clients::State as AppState, invite_expiry::INVITE_EXPIRY},
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+// Let both the invitee and the sender know the invite changed state
+pub async fn notify_invite_state(
+    redis_client: Arc<Client>,
+    invite: &invites::Data,
+    invite_state: InviteState,
+) {
+    for user_id in [invite.user_id, invite.from_id] {
+        redis_client
+            .publish(
+                format!("priv_user:{}", user_id),
+                serde_json::to_string(&WebSocketMessage {
+                    record: Records::Message,
+                    queue: format!("chat-{}", invite.room_id),
+                    data: serde_json::json!({
+                        "invite_id": invite.id,
+                        "state": invite_state,
+                        "type": "invite",
+                    }),
+                })
+                .unwrap(),
+            )
+            .await
+            .ok();
+    }
+}
+
+pub async fn expire_invites(state: &AppState) -> Result<(), prisma_client_rust::QueryError> {
+    let stale_invites = state
+        .prisma_client
+        .invites()
+        .find_many(INVITE_EXPIRY.stale_filter())
+        .exec()
+        .await?;
+    // One guarded update per invite, a concurrent response or another instance's sweep wins
+    // and only the invites this sweep flipped are announced
+    for invite in stale_invites.iter() {
+        let expired = state
+            .prisma_client
+            .invites()
+            .update_many(
+                vec![
+                    invites::id::equals(invite.id.clone()),
+                    invites::state::equals(InviteState::Pending),
+                ],
+                vec![invites::state::set(InviteState::Expired)],
+            )
+            .exec()
+            .await?;
+        if expired == 1 {
+            notify_invite_state(state.redis_client.clone(), invite, InviteState::Expired).await;
+        }
+    }
+    Ok(())
+}
+
+pub fn spawn_invite_sweep(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(INVITE_EXPIRY.sweep_interval));
+        loop {
+            interval.tick().await;
+            if let Err(e) = expire_invites(&state).await {
+                println!("{:?}", e);
+            }
+        }
+    });
+}
@@ -1 +1,2 @@
+pub mod invite_code_param;
 pub mod invite_id_param;
@@ -1,6 +1,8 @@
 use crate::{
-    chat::interfaces::single_user_param::SingleUserParam,
-    prisma_client::client::{banned_users_room, rooms, user, users_rooms},
+    chat::{
+        interfaces::single_user_param::SingleUserParam, rooms::moderation::ban_user::is_banned,
+    },
+    prisma_client::client::{rooms, user, users_rooms, RoomKind},
     rejection::path::CustomPathDataRejection,
     shared::arc_clients::State as AppState,
     socket::interfaces::websocket_message::WebSocketMessage,
@@ -30,6 +32,26 @@ pub async fn invite_user(
         CustomPathDataRejection,
     >,
 ) -> (StatusCode, Json<InviteUserResponse>) {
+    if participant.room.as_ref().unwrap().archived {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(InviteUserResponse {
+                success: false,
+                error: Some("Chat is archived".to_string()),
+                http_code: 403,
+            }),
+        );
+    }
+    if participant.room.as_ref().unwrap().kind != RoomKind::Group {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(InviteUserResponse {
+                success: false,
+                error: Some("Direct conversations do not take invites".to_string()),
+                http_code: 403,
+            }),
+        );
+    }
     if participant.user_id as u32 == user_id {
         return (
             StatusCode::BAD_REQUEST,
@@ -69,17 +91,14 @@ pub async fn invite_user(
             }),
         );
     }
-    let is_banned = state
-        .prisma_client
-        .banned_users_room()
-        .find_first(vec![
-            banned_users_room::user_id::equals(user_id as i32),
-            banned_users_room::room_id::equals(participant.room_id),
-        ])
-        .exec()
-        .await;
+    let is_banned = is_banned(
+        state.prisma_client.clone(),
+        user_id as i32,
+        participant.room_id,
+    )
+    .await;
     let is_banned = match is_banned {
-        Ok(is_banned) => is_banned.is_some(),
+        Ok(is_banned) => is_banned,
         Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
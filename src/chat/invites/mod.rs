@@ -1,4 +1,10 @@
+pub mod cancel_invite;
+pub mod create_invite_link;
+pub mod expire_invites;
 pub mod interfaces;
 pub mod invite_user;
+pub mod redeem_invite_link;
 pub mod response_invite;
 pub mod retrieve_invite;
+pub mod retrieve_invite_links;
+pub mod revoke_invite_link;
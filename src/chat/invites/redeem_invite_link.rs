@@ -0,0 +1,313 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use prisma_client_rust::{operator::or, QueryError};
+use rustis::commands::PubSubCommands;
+use serde::Serialize;
+
+use crate::{
+    chat::rooms::moderation::{ban_user::is_banned, kick_user::kick_cooldown},
+    prisma_client::client::{invite_links, rooms, user, users_rooms},
+    rejection::path::CustomPathDataRejection,
+    shared::{arc_clients::State as AppState, room_limits::RoomLimits},
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+use super::interfaces::invite_code_param::InviteCodeParam;
+
+#[derive(Serialize)]
+pub struct RedeemInviteLinkResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+enum RedeemError {
+    Query(QueryError),
+    // Another redemption counted its use first
+    Busy,
+}
+
+impl From<QueryError> for RedeemError {
+    fn from(value: QueryError) -> Self {
+        RedeemError::Query(value)
+    }
+}
+
+pub async fn redeem_invite_link(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Path(InviteCodeParam { code }), _): WithRejection<
+        Path<InviteCodeParam>,
+        CustomPathDataRejection,
+    >,
+) -> (StatusCode, Json<RedeemInviteLinkResponse>) {
+    let link = state
+        .prisma_client
+        .invite_links()
+        .find_first(vec![
+            invite_links::id::equals(code),
+            invite_links::revoked::equals(false),
+            or(vec![
+                invite_links::expires_at::equals(None),
+                invite_links::expires_at::gt(chrono::Utc::now().into()),
+            ]),
+        ])
+        .with(invite_links::room::fetch())
+        .exec()
+        .await;
+    let link = match link {
+        Ok(Some(link)) => link,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 404,
+                    chat_id: None,
+                    error: Some("Invite link not found".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 500,
+                    chat_id: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    if let Some(max_uses) = link.max_uses {
+        if link.uses >= max_uses {
+            return (
+                StatusCode::GONE,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 410,
+                    chat_id: None,
+                    error: Some("Invite link has been used up".to_string()),
+                }),
+            );
+        }
+    }
+    let room = link.room.clone().unwrap();
+    if room.archived {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(RedeemInviteLinkResponse {
+                success: false,
+                http_code: 403,
+                chat_id: None,
+                error: Some("Chat is archived".to_string()),
+            }),
+        );
+    }
+    match is_banned(state.prisma_client.clone(), user.id, room.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 403,
+                    chat_id: None,
+                    error: Some("You are banned from this room.".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 500,
+                    chat_id: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    match kick_cooldown(state.redis_client.clone(), user.id, room.id).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 403,
+                    chat_id: None,
+                    error: Some("You were recently kicked from this room".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 500,
+                    chat_id: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let is_participant = state
+        .prisma_client
+        .users_rooms()
+        .find_first(vec![
+            users_rooms::user_id::equals(user.id),
+            users_rooms::room_id::equals(room.id),
+        ])
+        .exec()
+        .await;
+    match is_participant {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 409,
+                    chat_id: None,
+                    error: Some("Already participant".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 500,
+                    chat_id: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let participant_count = state
+        .prisma_client
+        .users_rooms()
+        .count(vec![users_rooms::room_id::equals(room.id)])
+        .exec()
+        .await;
+    match participant_count {
+        Ok(participant_count) => {
+            if RoomLimits::is_full(room.capacity, participant_count) {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(RedeemInviteLinkResponse {
+                        success: false,
+                        http_code: 400,
+                        chat_id: None,
+                        error: Some("Chat is full".to_string()),
+                    }),
+                );
+            }
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 500,
+                    chat_id: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    // Only count the use when nobody else redeemed the link in the meantime, that keeps
+    // concurrent redemptions from exceeding max uses. The use is given back if the join fails
+    let (link_id, link_uses, room_id, user_id) = (link.id.clone(), link.uses, room.id, user.id);
+    let redemption = state
+        .prisma_client
+        ._transaction()
+        .run(|client| async move {
+            let claimed = client
+                .invite_links()
+                .update_many(
+                    vec![
+                        invite_links::id::equals(link_id),
+                        invite_links::uses::equals(link_uses),
+                    ],
+                    vec![invite_links::uses::increment(1)],
+                )
+                .exec()
+                .await?;
+            if claimed != 1 {
+                return Err(RedeemError::Busy);
+            }
+            client
+                .users_rooms()
+                .create(
+                    user::UniqueWhereParam::IdEquals(user_id),
+                    rooms::UniqueWhereParam::IdEquals(room_id),
+                    vec![],
+                )
+                .exec()
+                .await?;
+            Ok(())
+        })
+        .await;
+    match redemption {
+        Ok(_) => {}
+        Err(RedeemError::Busy) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 409,
+                    chat_id: None,
+                    error: Some("Invite link is busy, try again".to_string()),
+                }),
+            )
+        }
+        Err(RedeemError::Query(_)) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RedeemInviteLinkResponse {
+                    success: false,
+                    http_code: 500,
+                    chat_id: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    state
+        .redis_client
+        .publish(
+            format!("chat:{}", room.id),
+            serde_json::to_string(&WebSocketMessage {
+                record: Records::ParticipantJoined,
+                queue: format!("chat:{}", room.id),
+                data: serde_json::json!({
+                    "user_id": user.id,
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+    (
+        StatusCode::CREATED,
+        Json(RedeemInviteLinkResponse {
+            success: true,
+            http_code: 201,
+            chat_id: Some(room.id),
+            error: None,
+        }),
+    )
+}
@@ -4,19 +4,23 @@ use axum::{
     Extension, Json,
 };
 use axum_extra::extract::WithRejection;
+use prisma_client_rust::QueryError;
 use rustis::commands::PubSubCommands;
 use serde::{Deserialize, Serialize};
 use validator::{Validate, ValidationError};
 
 use crate::{
+    chat::rooms::moderation::kick_user::kick_cooldown,
     error::validation_error::ValidationError as CustomValidationError,
-    prisma_client::client::{invites, rooms, user, users_rooms},
+    prisma_client::client::{invites, rooms, user, users_rooms, InviteState},
     rejection::{json::CustomJsonDataRejection, path::CustomPathDataRejection},
-    shared::arc_clients::State as AppState,
+    shared::{
+        arc_clients::State as AppState, invite_expiry::INVITE_EXPIRY, room_limits::RoomLimits,
+    },
     socket::interfaces::websocket_message::WebSocketMessage,
 };
 
-use super::interfaces::invite_id_param::InviteIdParam;
+use super::{expire_invites::notify_invite_state, interfaces::invite_id_param::InviteIdParam};
 
 #[derive(Serialize)]
 pub struct InviteUserResponse {
@@ -47,9 +51,42 @@ pub struct InviteUserRequest {
     pub reaction: Option<InviteUserReaction>,
 }
 
+enum AcceptInviteError {
+    Query(QueryError),
+    // Cancelled, expired or answered by another request first
+    NotPending,
+}
+
+impl From<QueryError> for AcceptInviteError {
+    fn from(value: QueryError) -> Self {
+        AcceptInviteError::Query(value)
+    }
+}
+
+// Moves a pending invite to its final state, false when it was no longer pending
+async fn settle_invite(
+    state: &AppState,
+    invite_id: &str,
+    new_state: InviteState,
+) -> Result<bool, QueryError> {
+    let settled = state
+        .prisma_client
+        .invites()
+        .update_many(
+            vec![
+                invites::id::equals(invite_id.to_string()),
+                invites::state::equals(InviteState::Pending),
+            ],
+            vec![invites::state::set(new_state)],
+        )
+        .exec()
+        .await?;
+    Ok(settled == 1)
+}
+
 pub async fn invite_response(
     State(state): State<AppState>,
-    Extension(participant): Extension<users_rooms::Data>,
+    Extension(user): Extension<user::Data>,
     WithRejection(Path(InviteIdParam { invite_id }), _): WithRejection<
         Path<InviteIdParam>,
         CustomPathDataRejection,
@@ -67,13 +104,10 @@ pub async fn invite_response(
                 .invites()
                 .find_first(vec![
                     invites::id::equals(invite_id),
-                    invites::room_id::equals(participant.room_id),
-                    // invite cannot be older than 1 minute
-                    invites::created_at::gt(
-                        (chrono::Utc::now() - chrono::Duration::minutes(1)).into(),
-                    ),
-                    invites::user_id::equals(participant.user_id as i32),
+                    invites::user_id::equals(user.id),
+                    invites::state::equals(InviteState::Pending),
                 ])
+                .with(invites::room::fetch())
                 .exec()
                 .await;
             let invite = match invite {
@@ -103,20 +137,43 @@ pub async fn invite_response(
                     )
                 }
             };
-            let room = state
-                .prisma_client
-                .rooms()
-                .find_unique(rooms::UniqueWhereParam::IdEquals(participant.room_id))
-                .exec()
-                .await;
+            if INVITE_EXPIRY.is_expired(invite.created_at) {
+                // The sweep may expire it at the same time, only the one that changed it notifies
+                let expiration = settle_invite(&state, &invite.id, InviteState::Expired).await;
+                if matches!(expiration, Ok(true)) {
+                    notify_invite_state(state.redis_client.clone(), &invite, InviteState::Expired)
+                        .await;
+                }
+                return (
+                    StatusCode::GONE,
+                    Json(InviteUserResponse {
+                        success: false,
+                        http_code: 410,
+                        error: Some("Invite has expired".to_string()),
+                        validation_errors: None,
+                    }),
+                );
+            }
+            let room = invite.room.clone().unwrap();
+            if room.archived {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(InviteUserResponse {
+                        success: false,
+                        http_code: 403,
+                        error: Some("Chat is archived".to_string()),
+                        validation_errors: None,
+                    }),
+                );
+            }
             let is_room_full = state
                 .prisma_client
                 .users_rooms()
-                .count(vec![users_rooms::room_id::equals(participant.room_id)])
+                .count(vec![users_rooms::room_id::equals(invite.room_id)])
                 .exec()
                 .await;
             let is_room_full = match is_room_full {
-                Ok(is_room_full) => is_room_full >= room.unwrap().unwrap().capacity as i64,
+                Ok(participant_count) => RoomLimits::is_full(room.capacity, participant_count),
                 Err(_) => {
                     return (
                         StatusCode::INTERNAL_SERVER_ERROR,
@@ -130,19 +187,20 @@ pub async fn invite_response(
                 }
             };
             if is_room_full {
-                let invite = state
-                    .prisma_client
-                    .invites()
-                    .update(
-                        invites::UniqueWhereParam::IdEquals(invite.id),
-                        vec![invites::state::set(
-                            crate::prisma_client::client::InviteState::Declined,
-                        )],
-                    )
-                    .exec()
-                    .await;
+                let invite = settle_invite(&state, &invite.id, InviteState::Declined).await;
                 match invite {
-                    Ok(_) => {}
+                    Ok(true) => {}
+                    Ok(false) => {
+                        return (
+                            StatusCode::CONFLICT,
+                            Json(InviteUserResponse {
+                                success: false,
+                                http_code: 409,
+                                error: Some("Invite is no longer pending".to_string()),
+                                validation_errors: None,
+                            }),
+                        )
+                    }
                     Err(_) => {
                         return (
                             StatusCode::INTERNAL_SERVER_ERROR,
@@ -168,18 +226,23 @@ pub async fn invite_response(
 
             match reaction {
                 InviteUserReaction::Accept => {
-                    let participant_insertion = state
-                        .prisma_client
-                        .users_rooms()
-                        .create(
-                            user::UniqueWhereParam::IdEquals(participant.user_id as i32),
-                            rooms::UniqueWhereParam::IdEquals(participant.room_id),
-                            vec![],
-                        )
-                        .exec()
-                        .await;
-                    match participant_insertion {
-                        Ok(_) => {}
+                    // An invite doesn't lift the cooldown of a kick, it stays pending until then
+                    match kick_cooldown(state.redis_client.clone(), user.id, invite.room_id).await {
+                        Ok(None) => {}
+                        Ok(Some(cooldown)) => {
+                            return (
+                                StatusCode::FORBIDDEN,
+                                Json(InviteUserResponse {
+                                    success: false,
+                                    http_code: 403,
+                                    error: Some(format!(
+                                        "You were kicked from this room, try again in {} seconds",
+                                        cooldown
+                                    )),
+                                    validation_errors: None,
+                                }),
+                            )
+                        }
                         Err(_) => {
                             return (
                                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -192,43 +255,54 @@ pub async fn invite_response(
                             )
                         }
                     };
-                    state
-                    .redis_client
-                    .publish(
-                        format!("chat:{}", participant.user_id),
-                        serde_json::to_string(&WebSocketMessage {
-                            record: crate::socket::interfaces::websocket_message::Records::ParticipantJoined,
-                            data: serde_json::json!({}),
-                            queue: participant.user_id.to_string(),
-                        })
-                        .unwrap(),
-                    )
-                    .await
-                    .unwrap();
-                    let invite = state
+                    // Claiming the invite and joining happen together, a cancelled or expired
+                    // invite can't add the user and a failed join leaves it pending
+                    let (invite_id, room_id, user_id) =
+                        (invite.id.clone(), invite.room_id, user.id);
+                    let acceptance = state
                         .prisma_client
-                        .invites()
-                        .update(
-                            invites::UniqueWhereParam::IdEquals(invite.id),
-                            vec![invites::state::set(
-                                crate::prisma_client::client::InviteState::Accepted,
-                            )],
-                        )
-                        .exec()
+                        ._transaction()
+                        .run(|client| async move {
+                            let claimed = client
+                                .invites()
+                                .update_many(
+                                    vec![
+                                        invites::id::equals(invite_id),
+                                        invites::state::equals(InviteState::Pending),
+                                    ],
+                                    vec![invites::state::set(InviteState::Accepted)],
+                                )
+                                .exec()
+                                .await?;
+                            if claimed != 1 {
+                                return Err(AcceptInviteError::NotPending);
+                            }
+                            client
+                                .users_rooms()
+                                .create(
+                                    user::UniqueWhereParam::IdEquals(user_id),
+                                    rooms::UniqueWhereParam::IdEquals(room_id),
+                                    vec![],
+                                )
+                                .exec()
+                                .await?;
+                            Ok(())
+                        })
                         .await;
-                    match invite {
-                        Ok(_) => {
+                    match acceptance {
+                        Ok(_) => {}
+                        Err(AcceptInviteError::NotPending) => {
                             return (
-                                StatusCode::OK,
+                                StatusCode::CONFLICT,
                                 Json(InviteUserResponse {
-                                    success: true,
-                                    http_code: 200,
-                                    error: None,
+                                    success: false,
+                                    http_code: 409,
+                                    error: Some("Invite is no longer pending".to_string()),
                                     validation_errors: None,
                                 }),
-                            );
+                            )
                         }
-                        Err(_) => {
+                        Err(AcceptInviteError::Query(_)) => {
                             return (
                                 StatusCode::INTERNAL_SERVER_ERROR,
                                 Json(InviteUserResponse {
@@ -239,22 +313,47 @@ pub async fn invite_response(
                                 }),
                             )
                         }
-                    }
+                    };
+                    state
+                    .redis_client
+                    .publish(
+                        format!("chat:{}", invite.room_id),
+                        serde_json::to_string(&WebSocketMessage {
+                            record: crate::socket::interfaces::websocket_message::Records::ParticipantJoined,
+                            data: serde_json::json!({
+                                "user_id": user.id,
+                            }),
+                            queue: format!("chat:{}", invite.room_id),
+                        })
+                        .unwrap(),
+                    )
+                    .await
+                    .ok();
+                    (
+                        StatusCode::OK,
+                        Json(InviteUserResponse {
+                            success: true,
+                            http_code: 200,
+                            error: None,
+                            validation_errors: None,
+                        }),
+                    )
                 }
                 InviteUserReaction::Decline => {
-                    let invite = state
-                        .prisma_client
-                        .invites()
-                        .update(
-                            invites::UniqueWhereParam::IdEquals(invite.id),
-                            vec![invites::state::set(
-                                crate::prisma_client::client::InviteState::Declined,
-                            )],
-                        )
-                        .exec()
-                        .await;
+                    let invite = settle_invite(&state, &invite.id, InviteState::Declined).await;
                     match invite {
-                        Ok(_) => {
+                        Ok(false) => {
+                            return (
+                                StatusCode::CONFLICT,
+                                Json(InviteUserResponse {
+                                    success: false,
+                                    http_code: 409,
+                                    error: Some("Invite is no longer pending".to_string()),
+                                    validation_errors: None,
+                                }),
+                            );
+                        }
+                        Ok(true) => {
                             return (
                                 StatusCode::OK,
                                 Json(InviteUserResponse {
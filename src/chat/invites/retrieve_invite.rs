@@ -1,6 +1,7 @@
 use crate::{
-    prisma_client::client::invites, rejection::path::CustomPathDataRejection,
-    shared::arc_clients::State as AppState,
+    prisma_client::client::invites,
+    rejection::path::CustomPathDataRejection,
+    shared::{arc_clients::State as AppState, invite_expiry::INVITE_EXPIRY},
 };
 use axum::{
     extract::{Path, State},
@@ -59,14 +60,12 @@ pub async fn retrieve_invite(
     WithRejection(Path(params), _): WithRejection<Path<InviteIdParam>, CustomPathDataRejection>,
 ) -> (StatusCode, Json<RetrieveInviteResponse>) {
     let invite_id = params.invite_id;
+    let mut filters = INVITE_EXPIRY.active_filter();
+    filters.push(invites::id::equals(invite_id));
     let invite = state
         .prisma_client
         .invites()
-        .find_first(vec![
-            invites::id::equals(invite_id),
-            invites::state::equals(crate::prisma_client::client::InviteState::Pending),
-            invites::created_at::gt((chrono::Utc::now() - chrono::Duration::days(1)).into()),
-        ])
+        .find_first(filters)
         .with(invites::from::fetch())
         .with(invites::user::fetch())
         .exec()
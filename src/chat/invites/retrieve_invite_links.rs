@@ -0,0 +1,57 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{invite_links, users_rooms},
+    shared::arc_clients::State as AppState,
+};
+
+use super::create_invite_link::InviteLink;
+
+#[derive(Serialize)]
+pub struct RetrieveInviteLinksResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<Vec<InviteLink>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn retrieve_invite_links(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+) -> (StatusCode, Json<RetrieveInviteLinksResponse>) {
+    let links = state
+        .prisma_client
+        .invite_links()
+        .find_many(vec![
+            invite_links::room_id::equals(participant.room_id),
+            invite_links::revoked::equals(false),
+        ])
+        .order_by(invite_links::created_at::order(
+            prisma_client_rust::Direction::Desc,
+        ))
+        .exec()
+        .await;
+    match links {
+        Ok(links) => (
+            StatusCode::OK,
+            Json(RetrieveInviteLinksResponse {
+                success: true,
+                http_code: 200,
+                links: Some(links.into_iter().map(InviteLink::from).collect()),
+                error: None,
+            }),
+        ),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(RetrieveInviteLinksResponse {
+                success: false,
+                http_code: 500,
+                links: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        ),
+    }
+}
@@ -0,0 +1,97 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{invite_links, users_rooms},
+    rejection::path::CustomPathDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+use super::interfaces::invite_code_param::InviteCodeParam;
+
+#[derive(Serialize)]
+pub struct RevokeInviteLinkErrorResponse {
+    pub success: bool,
+    pub http_code: u16,
+    pub error: String,
+}
+
+pub async fn revoke_invite_link(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(InviteCodeParam { code }), _): WithRejection<
+        Path<InviteCodeParam>,
+        CustomPathDataRejection,
+    >,
+) -> Result<StatusCode, (StatusCode, Json<RevokeInviteLinkErrorResponse>)> {
+    let link = state
+        .prisma_client
+        .invite_links()
+        .find_first(vec![
+            invite_links::id::equals(code),
+            invite_links::room_id::equals(participant.room_id),
+            invite_links::revoked::equals(false),
+        ])
+        .exec()
+        .await;
+    let link = match link {
+        Ok(Some(link)) => link,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(RevokeInviteLinkErrorResponse {
+                    success: false,
+                    http_code: 404,
+                    error: "Invite link not found".to_string(),
+                }),
+            ));
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RevokeInviteLinkErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ));
+        }
+    };
+    // Only the creator of the link or the room owner may revoke it
+    let is_owner = participant.room.unwrap().user_id == Some(participant.user_id);
+    if link.creator_id != participant.user_id && !is_owner {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(RevokeInviteLinkErrorResponse {
+                success: false,
+                http_code: 403,
+                error: "You are not allowed to revoke this invite link".to_string(),
+            }),
+        ));
+    }
+    let revoke = state
+        .prisma_client
+        .invite_links()
+        .update(
+            invite_links::UniqueWhereParam::IdEquals(link.id),
+            vec![invite_links::revoked::set(true)],
+        )
+        .exec()
+        .await;
+    match revoke {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(RevokeInviteLinkErrorResponse {
+                success: false,
+                http_code: 500,
+                error: "Internal server error".to_string(),
+            }),
+        )),
+    }
+}
@@ -0,0 +1,99 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use validator::Validate;
+
+use crate::{
+    prisma_client::client::{scheduled_messages, users_rooms, ScheduledState},
+    rejection::path::CustomPathDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+use super::{
+    interfaces::scheduled_params::ScheduledIdParam,
+    schedule_message::{scheduled_validation_response, ScheduledMessageResponse},
+};
+
+pub async fn cancel_scheduled(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<Path<ScheduledIdParam>, CustomPathDataRejection>,
+) -> Result<StatusCode, (StatusCode, Json<ScheduledMessageResponse>)> {
+    if let Err(validation_errors) = params.validate() {
+        return Err(scheduled_validation_response(validation_errors));
+    }
+    let owned = vec![
+        scheduled_messages::id::equals(params.scheduled_id),
+        scheduled_messages::user_id::equals(participant.user_id),
+        scheduled_messages::room_id::equals(participant.room_id),
+    ];
+    // Once claimed by the scheduler the message can no longer be cancelled
+    let mut filters = owned.clone();
+    filters.push(scheduled_messages::state::in_vec(vec![
+        ScheduledState::Pending,
+        ScheduledState::Failed,
+    ]));
+    let deleted = state
+        .prisma_client
+        .scheduled_messages()
+        .delete_many(filters)
+        .exec()
+        .await;
+    match deleted {
+        Ok(0) => {}
+        Ok(_) => return Ok(StatusCode::NO_CONTENT),
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ScheduledMessageResponse {
+                    success: false,
+                    http_code: 500,
+                    scheduled: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            ))
+        }
+    };
+    let scheduled = state
+        .prisma_client
+        .scheduled_messages()
+        .find_first(owned)
+        .exec()
+        .await;
+    match scheduled {
+        Ok(Some(_)) => Err((
+            StatusCode::CONFLICT,
+            Json(ScheduledMessageResponse {
+                success: false,
+                http_code: 409,
+                scheduled: None,
+                validation_errors: None,
+                error: Some("Scheduled message was already sent".to_string()),
+            }),
+        )),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ScheduledMessageResponse {
+                success: false,
+                http_code: 404,
+                scheduled: None,
+                validation_errors: None,
+                error: Some("Scheduled message not found".to_string()),
+            }),
+        )),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ScheduledMessageResponse {
+                success: false,
+                http_code: 500,
+                scheduled: None,
+                validation_errors: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        )),
+    }
+}
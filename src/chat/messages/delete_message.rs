@@ -4,17 +4,25 @@ use axum::{
     Extension, Json,
 };
 use axum_extra::extract::WithRejection;
+use prisma_client_rust::operator::or;
 use rustis::commands::PubSubCommands;
 use serde::Serialize;
 
 use crate::{
-    prisma_client::client::{messages, user},
+    chat::rooms::moderation::audit_log::{record_action, ModerationRecord},
+    prisma_client::client::{attachments, messages, user, ModerationAction},
     rejection::path::CustomPathDataRejection,
     shared::arc_clients::State as AppState,
     socket::interfaces::websocket_message::WebSocketMessage,
 };
 
-use super::interfaces::retrieve_message_params::RetrieveSingleMessageParam;
+use super::{
+    interfaces::retrieve_message_params::RetrieveSingleMessageParam,
+    pin_message::publish_pin_event,
+    process_attachment::quarantine_key,
+    read_messages::invalidate_unread,
+    retrieve_thread::{publish_thread_event, refresh_thread},
+};
 
 #[derive(Serialize)]
 pub struct DeleteMessageErrorResponse {
@@ -29,6 +37,26 @@ pub struct WebsocketDeleteMessageData {
     pub action: String,
 }
 
+async fn publish_delete_event(state: &AppState, room_id: i32, message_id: i32) {
+    let prefix = format!("chat:{}", room_id);
+    state
+        .redis_client
+        .publish(
+            prefix.clone(),
+            serde_json::to_string(&WebSocketMessage {
+                record: crate::socket::interfaces::websocket_message::Records::Message,
+                queue: prefix,
+                data: serde_json::json!(WebsocketDeleteMessageData {
+                    message_id,
+                    action: "delete".to_string(),
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+}
+
 pub async fn delete_message(
     State(state): State<AppState>,
     Extension(user): Extension<user::Data>,
@@ -70,7 +98,7 @@ pub async fn delete_message(
             }
             let room = message.clone().room.unwrap();
             if message.user_id != user.id {
-                if room.user_id != user.id {
+                if room.user_id != Some(user.id) {
                     return Err((
                         StatusCode::FORBIDDEN,
                         Json(DeleteMessageErrorResponse {
@@ -81,6 +109,41 @@ pub async fn delete_message(
                     ));
                 }
             }
+            // Rows cascade with the message and its replies, stored files have to be removed by hand
+            let stored_attachments = state
+                .prisma_client
+                .attachments()
+                .find_many(vec![attachments::message::is(vec![or(vec![
+                    messages::id::equals(message_id),
+                    messages::parent_id::equals(Some(message_id)),
+                ])])])
+                .exec()
+                .await
+                .unwrap_or_default();
+            // Replies go with the message, clients and counters are told about each of them
+            let replies = state
+                .prisma_client
+                .messages()
+                .find_many(vec![messages::parent_id::equals(Some(message_id))])
+                .exec()
+                .await;
+            let replies = match replies {
+                Ok(replies) => replies,
+                Err(_) => {
+                    return Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(DeleteMessageErrorResponse {
+                            success: false,
+                            http_code: 500,
+                            error: "Internal server error".to_string(),
+                        }),
+                    ))
+                }
+            };
+            let newest_deleted_id = replies
+                .iter()
+                .map(|reply| reply.id)
+                .fold(message_id, i32::max);
             let delete_message = state
                 .prisma_client
                 .messages()
@@ -89,24 +152,50 @@ pub async fn delete_message(
                 .await;
             match delete_message {
                 Ok(_) => {
-                    let prefix = format!("chat:{}", room.id);
-                    state
-                        .redis_client
-                        .publish(
-                            prefix.clone(),
-                            serde_json::to_string(&WebSocketMessage {
-                                record:
-                                    crate::socket::interfaces::websocket_message::Records::Message,
-                                queue: prefix,
-                                data: serde_json::json!(WebsocketDeleteMessageData {
-                                    message_id: message_id,
-                                    action: "delete".to_string(),
-                                }),
-                            })
-                            .unwrap(),
+                    for attachment in stored_attachments {
+                        state.storage.delete(&attachment.storage_key).await.ok();
+                        state
+                            .storage
+                            .delete(&quarantine_key(&attachment.storage_key))
+                            .await
+                            .ok();
+                        if let Some(thumbnail_key) = attachment.thumbnail_key {
+                            state.storage.delete(&thumbnail_key).await.ok();
+                        }
+                    }
+                    invalidate_unread(&state, room.id, newest_deleted_id)
+                        .await
+                        .ok();
+                    // Owners removing someone else's message is a moderation action
+                    if message.user_id != user.id {
+                        record_action(
+                            state.prisma_client.clone(),
+                            ModerationRecord {
+                                action: ModerationAction::DeleteMessage,
+                                actor_id: user.id,
+                                room_id: room.id,
+                                target_id: Some(message.user_id),
+                                message_id: Some(message_id),
+                                reason: None,
+                            },
                         )
                         .await
                         .ok();
+                    }
+                    for deleted in replies.iter().chain(std::iter::once(&message)) {
+                        publish_delete_event(&state, room.id, deleted.id).await;
+                        // The pin goes away with the row, clients still need to drop it
+                        if deleted.pinned {
+                            publish_pin_event(&state, room.id, deleted.id, false).await;
+                        }
+                    }
+                    if let Some(parent_id) = message.parent_id {
+                        if let Ok(parent) =
+                            refresh_thread(state.prisma_client.clone(), parent_id).await
+                        {
+                            publish_thread_event(&state, &parent, message_id).await;
+                        }
+                    }
                     return Ok(StatusCode::NO_CONTENT);
                 }
                 Err(_) => {
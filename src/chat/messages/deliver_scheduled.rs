@@ -0,0 +1,296 @@
+use prisma_client_rust::QueryError;
+use rustis::commands::PubSubCommands;
+
+use crate::{
+    prisma_client::client::{
+        messages, rooms, scheduled_messages, user, users_rooms, ScheduledState,
+    },
+    shared::{arc_clients::State as AppState, scheduled_messages::SCHEDULED_MESSAGES},
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+use super::{
+    markdown::format_message,
+    middlewares::can_talk::{check_participant, CanTalkError},
+    send_message::{find_thread_parent, publish_message},
+};
+
+enum DeliveryError {
+    Query(QueryError),
+    // Another instance took the message over after our claim went stale
+    ClaimLost,
+}
+
+impl From<QueryError> for DeliveryError {
+    fn from(value: QueryError) -> Self {
+        DeliveryError::Query(value)
+    }
+}
+
+enum Delivery {
+    Sent(messages::Data),
+    Failed(String),
+    Retry,
+    Skipped,
+}
+
+// Instances race for due messages, only the one whose update matched delivers it
+async fn claim_scheduled(
+    state: &AppState,
+    scheduled_id: i32,
+) -> Result<Option<String>, QueryError> {
+    let claim_token = uuid::Uuid::new_v4().to_string();
+    let claimed = state
+        .prisma_client
+        .scheduled_messages()
+        .update_many(
+            vec![
+                scheduled_messages::id::equals(scheduled_id),
+                scheduled_messages::state::equals(ScheduledState::Pending),
+                scheduled_messages::send_at::lte(chrono::Utc::now().into()),
+            ],
+            vec![
+                scheduled_messages::state::set(ScheduledState::Sending),
+                scheduled_messages::claim_token::set(Some(claim_token.clone())),
+                scheduled_messages::claimed_at::set(Some(chrono::Utc::now().into())),
+            ],
+        )
+        .exec()
+        .await?;
+    Ok((claimed == 1).then_some(claim_token))
+}
+
+// Claims left behind by an instance that stopped mid delivery go back to the queue
+async fn release_stale_claims(state: &AppState) -> Result<i64, QueryError> {
+    state
+        .prisma_client
+        .scheduled_messages()
+        .update_many(
+            vec![
+                scheduled_messages::state::equals(ScheduledState::Sending),
+                scheduled_messages::claimed_at::lt(SCHEDULED_MESSAGES.stale_claim_cutoff()),
+            ],
+            vec![
+                scheduled_messages::state::set(ScheduledState::Pending),
+                scheduled_messages::claim_token::set(None),
+                scheduled_messages::claimed_at::set(None),
+            ],
+        )
+        .exec()
+        .await
+}
+
+async fn deliver_scheduled(
+    state: &AppState,
+    scheduled: &scheduled_messages::Data,
+    claim_token: String,
+) -> Result<Delivery, QueryError> {
+    // Same checks as the `can_talk` middleware, minus the rate limits paid when scheduling
+    let participant = state
+        .prisma_client
+        .users_rooms()
+        .find_first(vec![
+            users_rooms::user_id::equals(scheduled.user_id),
+            users_rooms::room_id::equals(scheduled.room_id),
+        ])
+        .with(users_rooms::room::fetch())
+        .exec()
+        .await?;
+    let participant = match participant {
+        Some(participant) => participant,
+        None => return Ok(Delivery::Failed("Not A Participant".to_string())),
+    };
+    match check_participant(state, &participant).await {
+        Ok(_) => {}
+        Err(CanTalkError::InternalError) | Err(CanTalkError::TooFast(_)) => {
+            return Ok(Delivery::Retry)
+        }
+        Err(error) => return Ok(Delivery::Failed(error.reason().to_string())),
+    };
+    if let Some(parent_id) = scheduled.parent_id {
+        if find_thread_parent(state, scheduled.room_id, parent_id)
+            .await?
+            .is_none()
+        {
+            return Ok(Delivery::Failed("Parent message not found".to_string()));
+        }
+    }
+    let formatted = format_message(&scheduled.message, scheduled.format);
+    let (scheduled_id, text, format, parent_id, user_id, room_id) = (
+        scheduled.id,
+        scheduled.message.clone(),
+        scheduled.format,
+        scheduled.parent_id,
+        scheduled.user_id,
+        scheduled.room_id,
+    );
+    // The message only exists once the scheduled row is marked sent, so a claim released
+    // mid delivery can't end up sent twice
+    let message = state
+        .prisma_client
+        ._transaction()
+        .run(|client| async move {
+            let marked = client
+                .scheduled_messages()
+                .update_many(
+                    vec![
+                        scheduled_messages::id::equals(scheduled_id),
+                        scheduled_messages::state::equals(ScheduledState::Sending),
+                        scheduled_messages::claim_token::equals(Some(claim_token)),
+                    ],
+                    vec![
+                        scheduled_messages::state::set(ScheduledState::Sent),
+                        scheduled_messages::claim_token::set(None),
+                    ],
+                )
+                .exec()
+                .await?;
+            if marked != 1 {
+                return Err(DeliveryError::ClaimLost);
+            }
+            let mut create_params = vec![
+                messages::format::set(format),
+                messages::rendered::set(formatted.rendered),
+            ];
+            if let Some(parent_id) = parent_id {
+                create_params.push(messages::parent::connect(
+                    messages::UniqueWhereParam::IdEquals(parent_id),
+                ));
+            }
+            let message = client
+                .messages()
+                .create(
+                    text,
+                    user::UniqueWhereParam::IdEquals(user_id),
+                    rooms::UniqueWhereParam::IdEquals(room_id),
+                    create_params,
+                )
+                .exec()
+                .await?;
+            client
+                .scheduled_messages()
+                .update(
+                    scheduled_messages::UniqueWhereParam::IdEquals(scheduled_id),
+                    vec![scheduled_messages::sent_message::connect(
+                        messages::UniqueWhereParam::IdEquals(message.id),
+                    )],
+                )
+                .exec()
+                .await?;
+            Ok(message)
+        })
+        .await;
+    match message {
+        Ok(message) => Ok(Delivery::Sent(message)),
+        Err(DeliveryError::ClaimLost) => Ok(Delivery::Skipped),
+        Err(DeliveryError::Query(e)) => Err(e),
+    }
+}
+
+// Only touches the row while our claim still holds
+async fn settle_claim(
+    state: &AppState,
+    scheduled: &scheduled_messages::Data,
+    claim_token: String,
+    failure: Option<String>,
+) -> Result<i64, QueryError> {
+    let next_state = match failure {
+        Some(_) => ScheduledState::Failed,
+        None => ScheduledState::Pending,
+    };
+    state
+        .prisma_client
+        .scheduled_messages()
+        .update_many(
+            vec![
+                scheduled_messages::id::equals(scheduled.id),
+                scheduled_messages::claim_token::equals(Some(claim_token)),
+            ],
+            vec![
+                scheduled_messages::state::set(next_state),
+                scheduled_messages::failure::set(failure),
+                scheduled_messages::claim_token::set(None),
+                scheduled_messages::claimed_at::set(None),
+            ],
+        )
+        .exec()
+        .await
+}
+
+async fn notify_scheduled_failure(
+    state: &AppState,
+    scheduled: &scheduled_messages::Data,
+    failure: &str,
+) {
+    state
+        .redis_client
+        .publish(
+            format!("priv_user:{}", scheduled.user_id),
+            serde_json::to_string(&WebSocketMessage {
+                record: Records::Message,
+                queue: format!("chat-{}", scheduled.room_id),
+                data: serde_json::json!({
+                    "scheduled_id": scheduled.id,
+                    "chat_id": scheduled.room_id,
+                    "error": failure,
+                    "type": "scheduled_failed",
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+}
+
+pub async fn deliver_due_messages(state: &AppState) -> Result<(), QueryError> {
+    release_stale_claims(state).await?;
+    let due = state
+        .prisma_client
+        .scheduled_messages()
+        .find_many(vec![
+            scheduled_messages::state::equals(ScheduledState::Pending),
+            scheduled_messages::send_at::lte(chrono::Utc::now().into()),
+        ])
+        .order_by(scheduled_messages::send_at::order(
+            prisma_client_rust::Direction::Asc,
+        ))
+        .take(SCHEDULED_MESSAGES.batch_size)
+        .exec()
+        .await?;
+    for scheduled in due.iter() {
+        let claim_token = match claim_scheduled(state, scheduled.id).await? {
+            Some(claim_token) => claim_token,
+            None => continue,
+        };
+        match deliver_scheduled(state, scheduled, claim_token.clone()).await {
+            Ok(Delivery::Sent(message)) => publish_message(state, &message).await,
+            Ok(Delivery::Failed(failure)) => {
+                let settled =
+                    settle_claim(state, scheduled, claim_token, Some(failure.clone())).await;
+                if let Ok(1) = settled {
+                    notify_scheduled_failure(state, scheduled, &failure).await;
+                }
+            }
+            // Left for the next tick, or for the stale claim release if this fails too
+            Ok(Delivery::Retry) | Err(_) => {
+                settle_claim(state, scheduled, claim_token, None).await.ok();
+            }
+            Ok(Delivery::Skipped) => {}
+        }
+    }
+    Ok(())
+}
+
+pub fn spawn_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            SCHEDULED_MESSAGES.poll_interval,
+        ));
+        loop {
+            interval.tick().await;
+            if let Err(e) = deliver_due_messages(&state).await {
+                println!("{:?}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,280 @@
+use axum::{
+    extract::{Json as ExtractJson, Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use rustis::commands::{HashCommands, PubSubCommands};
+use rustrict::CensorStr;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::{validate_all, ValidationError},
+    prisma_client::client::{messages, users_rooms},
+    rejection::{json::CustomJsonDataRejection, path::CustomPathDataRejection},
+    shared::arc_clients::State as AppState,
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+use super::{
+    interfaces::retrieve_message_params::RetrieveSingleMessageParam, markdown::format_message,
+    read_messages::unread_mentions_key, send_message::record_mentions,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct EditMessageBody {
+    #[validate(
+        required(message = "message is required"),
+        length(
+            min = 1,
+            max = 1000,
+            message = "message must be between 1 and 1000 characters"
+        )
+    )]
+    pub message: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct EditMessageResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct WebsocketEditMessageData {
+    pub message_id: i32,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rendered: Option<String>,
+    pub edited_at: chrono::DateTime<chrono::Utc>,
+    pub action: String,
+}
+
+pub async fn edit_message(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<
+        Path<RetrieveSingleMessageParam>,
+        CustomPathDataRejection,
+    >,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<EditMessageBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<EditMessageResponse>) {
+    match validate_all(&params, &body) {
+        Ok(_) => {
+            let content = body.message.unwrap();
+            let message = state
+                .prisma_client
+                .messages()
+                .find_first(vec![
+                    messages::id::equals(params.message_id),
+                    messages::room_id::equals(participant.room_id),
+                ])
+                .exec()
+                .await;
+            let message = match message {
+                Ok(Some(message)) => message,
+                Ok(None) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(EditMessageResponse {
+                            success: false,
+                            http_code: 404,
+                            message: None,
+                            edited_at: None,
+                            validation_errors: None,
+                            error: Some("Message not found".to_string()),
+                        }),
+                    )
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(EditMessageResponse {
+                            success: false,
+                            http_code: 500,
+                            message: None,
+                            edited_at: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            if message.user_id != participant.user_id {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(EditMessageResponse {
+                        success: false,
+                        http_code: 403,
+                        message: None,
+                        edited_at: None,
+                        validation_errors: None,
+                        error: Some("You can only edit your own messages".to_string()),
+                    }),
+                );
+            }
+            if message.message == content {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(EditMessageResponse {
+                        success: false,
+                        http_code: 400,
+                        message: None,
+                        edited_at: None,
+                        validation_errors: None,
+                        error: Some("Message is unchanged".to_string()),
+                    }),
+                );
+            }
+            // Edits keep the format the message was sent with
+            let formatted = format_message(&content, message.format);
+            if formatted.visible_text.is_inappropriate() {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(EditMessageResponse {
+                        success: false,
+                        http_code: 400,
+                        message: None,
+                        edited_at: None,
+                        validation_errors: None,
+                        error: Some("Message is inappropriate".to_string()),
+                    }),
+                );
+            }
+            let edited_at = chrono::Utc::now();
+            let (message_id, previous, updates) = (
+                message.id,
+                message.message.clone(),
+                vec![
+                    messages::message::set(content.clone()),
+                    messages::edited_at::set(Some(edited_at.into())),
+                    messages::rendered::set(formatted.rendered.clone()),
+                ],
+            );
+            // Keep the content being replaced so staff can review it later, the revision only
+            // exists if the edit went through
+            let update = state
+                .prisma_client
+                ._transaction()
+                .run(|client| async move {
+                    client
+                        .message_revisions()
+                        .create(
+                            previous,
+                            messages::UniqueWhereParam::IdEquals(message_id),
+                            vec![],
+                        )
+                        .exec()
+                        .await?;
+                    client
+                        .messages()
+                        .update(messages::UniqueWhereParam::IdEquals(message_id), updates)
+                        .exec()
+                        .await
+                })
+                .await;
+            let updated = match update {
+                Ok(updated) => updated,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(EditMessageResponse {
+                            success: false,
+                            http_code: 500,
+                            message: None,
+                            edited_at: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            // Users mentioned before already have their row, only names added by the edit
+            // get a mention. Their counters are dropped and seeded again on the next lookup
+            let mentioned_ids = record_mentions(&state, &updated).await;
+            for mentioned_id in mentioned_ids {
+                state
+                    .redis_client
+                    .hdel(
+                        unread_mentions_key(mentioned_id),
+                        participant.room_id.to_string(),
+                    )
+                    .await
+                    .ok();
+            }
+            let prefix = format!("chat:{}", participant.room_id);
+            state
+                .redis_client
+                .publish(
+                    prefix.clone(),
+                    serde_json::to_string(&WebSocketMessage {
+                        record: Records::Message,
+                        queue: prefix,
+                        data: serde_json::json!(WebsocketEditMessageData {
+                            message_id: message.id,
+                            message: content.clone(),
+                            rendered: formatted.rendered,
+                            edited_at,
+                            action: "edit".to_string(),
+                        }),
+                    })
+                    .unwrap(),
+                )
+                .await
+                .ok();
+            (
+                StatusCode::OK,
+                Json(EditMessageResponse {
+                    success: true,
+                    http_code: 200,
+                    message: Some(content),
+                    edited_at: Some(edited_at),
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::BAD_REQUEST,
+                Json(EditMessageResponse {
+                    success: false,
+                    http_code: 400,
+                    message: None,
+                    edited_at: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use axum::{
+    extract::{Json as ExtractJson, Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::Deserialize;
+use validator::Validate;
+
+use rustrict::CensorStr;
+
+use crate::{
+    prisma_client::client::{scheduled_messages, users_rooms, MessageFormat, ScheduledState},
+    rejection::{json::CustomJsonDataRejection, path::CustomPathDataRejection},
+    shared::{arc_clients::State as AppState, scheduled_messages::SCHEDULED_MESSAGES},
+};
+
+use super::{
+    interfaces::scheduled_params::ScheduledIdParam,
+    markdown::format_message,
+    schedule_message::{
+        invalid_send_at, scheduled_validation_response, ScheduledMessageResponse,
+        ScheduledMessageSummary,
+    },
+};
+
+#[derive(Deserialize, Validate)]
+pub struct EditScheduledBody {
+    #[validate(length(
+        min = 1,
+        max = 1000,
+        message = "message must be between 1 and 1000 characters"
+    ))]
+    pub message: Option<String>,
+    pub format: Option<MessageFormat>,
+    pub send_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Failed messages can be edited too, which queues them again
+pub async fn edit_scheduled(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<Path<ScheduledIdParam>, CustomPathDataRejection>,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<EditScheduledBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<ScheduledMessageResponse>) {
+    if let Err(validation_errors) = params.validate() {
+        return scheduled_validation_response(validation_errors);
+    }
+    if let Err(validation_errors) = body.validate() {
+        return scheduled_validation_response(validation_errors);
+    }
+    let scheduled = state
+        .prisma_client
+        .scheduled_messages()
+        .find_first(vec![
+            scheduled_messages::id::equals(params.scheduled_id),
+            scheduled_messages::user_id::equals(participant.user_id),
+            scheduled_messages::room_id::equals(participant.room_id),
+        ])
+        .exec()
+        .await;
+    let scheduled = match scheduled {
+        Ok(Some(scheduled)) => scheduled,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ScheduledMessageResponse {
+                    success: false,
+                    http_code: 404,
+                    scheduled: None,
+                    validation_errors: None,
+                    error: Some("Scheduled message not found".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ScheduledMessageResponse {
+                    success: false,
+                    http_code: 500,
+                    scheduled: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let editable = [ScheduledState::Pending, ScheduledState::Failed];
+    if !editable.contains(&scheduled.state) {
+        return (
+            StatusCode::CONFLICT,
+            Json(ScheduledMessageResponse {
+                success: false,
+                http_code: 409,
+                scheduled: None,
+                validation_errors: None,
+                error: Some("Scheduled message was already sent".to_string()),
+            }),
+        );
+    }
+    let message = body.message.unwrap_or(scheduled.message);
+    let format = body.format.unwrap_or(scheduled.format);
+    let send_at = body.send_at.unwrap_or_else(|| scheduled.send_at.into());
+    if !SCHEDULED_MESSAGES.is_valid_send_at(send_at) {
+        return invalid_send_at();
+    }
+    if format_message(&message, format)
+        .visible_text
+        .is_inappropriate()
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ScheduledMessageResponse {
+                success: false,
+                http_code: 400,
+                scheduled: None,
+                validation_errors: None,
+                error: Some("Message is inappropriate".to_string()),
+            }),
+        );
+    }
+    // Guarded on the state so an edit can't race the scheduler claiming the message
+    let updated = state
+        .prisma_client
+        .scheduled_messages()
+        .update_many(
+            vec![
+                scheduled_messages::id::equals(scheduled.id),
+                scheduled_messages::state::in_vec(editable.to_vec()),
+            ],
+            vec![
+                scheduled_messages::message::set(message),
+                scheduled_messages::format::set(format),
+                scheduled_messages::send_at::set(send_at.into()),
+                scheduled_messages::state::set(ScheduledState::Pending),
+                scheduled_messages::failure::set(None),
+            ],
+        )
+        .exec()
+        .await;
+    match updated {
+        Ok(1) => {}
+        Ok(_) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(ScheduledMessageResponse {
+                    success: false,
+                    http_code: 409,
+                    scheduled: None,
+                    validation_errors: None,
+                    error: Some("Scheduled message was already sent".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ScheduledMessageResponse {
+                    success: false,
+                    http_code: 500,
+                    scheduled: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let scheduled = state
+        .prisma_client
+        .scheduled_messages()
+        .find_unique(scheduled_messages::UniqueWhereParam::IdEquals(scheduled.id))
+        .exec()
+        .await;
+    match scheduled {
+        Ok(Some(scheduled)) => (
+            StatusCode::OK,
+            Json(ScheduledMessageResponse {
+                success: true,
+                http_code: 200,
+                scheduled: Some(ScheduledMessageSummary::from(scheduled)),
+                validation_errors: None,
+                error: None,
+            }),
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ScheduledMessageResponse {
+                success: false,
+                http_code: 404,
+                scheduled: None,
+                validation_errors: None,
+                error: Some("Scheduled message not found".to_string()),
+            }),
+        ),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ScheduledMessageResponse {
+                success: false,
+                http_code: 500,
+                scheduled: None,
+                validation_errors: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        ),
+    }
+}
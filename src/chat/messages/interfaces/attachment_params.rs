@@ -0,0 +1,14 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate)]
+pub struct AttachmentParams {
+    #[validate(length(min = 1, max = 64, message = "Attachment id invalid"))]
+    pub attachment_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct AttachmentQuery {
+    // Serves the generated thumbnail instead of the file
+    pub thumbnail: Option<bool>,
+}
@@ -1 +1,4 @@
+pub mod attachment_params;
+pub mod reaction_params;
 pub mod retrieve_message_params;
+pub mod scheduled_params;
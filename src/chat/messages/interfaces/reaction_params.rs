@@ -0,0 +1,31 @@
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
+
+// Emoji are made of non ascii symbols, which keeps plain words out of reactions
+fn validate_emoji(emoji: &str) -> Result<(), ValidationError> {
+    if emoji
+        .chars()
+        .all(|c| !c.is_ascii() && !c.is_whitespace() && !c.is_alphanumeric())
+    {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("invalid_emoji");
+        error.message = Some("Invalid emoji".into());
+        Err(error)
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ReactionParams {
+    #[validate(range(min = 1, message = "Message id invalid"))]
+    pub message_id: i32,
+    #[validate(
+        length(
+            min = 1,
+            max = 32,
+            message = "emoji must be between 1 and 32 characters"
+        ),
+        custom = "validate_emoji"
+    )]
+    pub emoji: String,
+}
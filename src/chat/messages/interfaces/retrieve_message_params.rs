@@ -9,6 +9,20 @@ pub struct RetrieveMessageParams {
     pub message_id: i32,
 }
 
+#[derive(Deserialize, Validate)]
+pub struct HistoryQuery {
+    #[validate(range(min = 1, max = 50, message = "Limit must be between 1 and 50"))]
+    pub limit: Option<i32>,
+    // Only one cursor can be used at a time, none returns the latest messages
+    #[validate(range(min = 1, message = "before invalid"))]
+    pub before: Option<i32>,
+    #[validate(range(min = 1, message = "after invalid"))]
+    pub after: Option<i32>,
+    // Jumps to a message, the page is centered on it
+    #[validate(range(min = 1, message = "around invalid"))]
+    pub around: Option<i32>,
+}
+
 #[derive(Deserialize, Validate)]
 pub struct RetrieveSingleMessageParam {
     #[validate(range(min = 1, message = "Message id invalid"))]
@@ -0,0 +1,8 @@
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate)]
+pub struct ScheduledIdParam {
+    #[validate(range(min = 1, message = "Scheduled message id invalid"))]
+    pub scheduled_id: i32,
+}
@@ -0,0 +1,183 @@
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::prisma_client::client::MessageFormat;
+
+pub struct FormattedMessage {
+    // What readers end up seeing, this is what the censor checks
+    pub visible_text: String,
+    pub rendered: Option<String>,
+}
+
+pub fn format_message(source: &str, format: MessageFormat) -> FormattedMessage {
+    match format {
+        MessageFormat::Plain => FormattedMessage {
+            visible_text: source.to_string(),
+            rendered: None,
+        },
+        MessageFormat::Markdown => {
+            let (rendered, visible_text) = render_markdown(source);
+            FormattedMessage {
+                visible_text,
+                rendered: Some(rendered),
+            }
+        }
+    }
+}
+
+fn escape_html(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+// Only absolute web and mail links survive, `javascript:` and relative urls are dropped
+fn is_safe_link(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    url.starts_with("https://") || url.starts_with("http://") || url.starts_with("mailto:")
+}
+
+// The readable text outside code spans and blocks, an `@name` in code doesn't ping anyone
+pub fn mention_text(source: &str, format: MessageFormat) -> String {
+    match format {
+        MessageFormat::Plain => source.to_string(),
+        MessageFormat::Markdown => {
+            let mut text = String::new();
+            let mut in_code_block = false;
+            for event in Parser::new(source) {
+                match event {
+                    Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+                    Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+                    Event::Text(content) | Event::Html(content) if !in_code_block => {
+                        text.push_str(&content)
+                    }
+                    Event::Code(_)
+                    | Event::SoftBreak
+                    | Event::HardBreak
+                    | Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::Item) => text.push(' '),
+                    _ => {}
+                }
+            }
+            text
+        }
+    }
+}
+
+// Renders bold, italic, code, links and quotes, every other construct is reduced to its text
+// and raw HTML is escaped rather than passed through. Returns the HTML and the visible text
+pub fn render_markdown(source: &str) -> (String, String) {
+    let mut html = String::new();
+    let mut text = String::new();
+    // Whether each open link was rendered as an anchor
+    let mut links: Vec<bool> = Vec::new();
+    for event in Parser::new(source) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph | Tag::Heading(..) => html.push_str("<p>"),
+                Tag::BlockQuote => html.push_str("<blockquote>"),
+                Tag::CodeBlock(_) => html.push_str("<pre><code>"),
+                Tag::Emphasis => html.push_str("<em>"),
+                Tag::Strong => html.push_str("<strong>"),
+                Tag::Link(_, url, _) => {
+                    let safe = is_safe_link(&url);
+                    if safe {
+                        html.push_str("<a href=\"");
+                        escape_html(&url, &mut html);
+                        html.push_str("\" rel=\"nofollow noopener noreferrer\" target=\"_blank\">");
+                    }
+                    links.push(safe);
+                }
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                Tag::Paragraph | Tag::Heading(..) => {
+                    html.push_str("</p>");
+                    text.push('\n');
+                }
+                Tag::Item => {
+                    html.push_str("<br>");
+                    text.push('\n');
+                }
+                Tag::BlockQuote => html.push_str("</blockquote>"),
+                Tag::CodeBlock(_) => {
+                    html.push_str("</code></pre>");
+                    text.push('\n');
+                }
+                Tag::Emphasis => html.push_str("</em>"),
+                Tag::Strong => html.push_str("</strong>"),
+                Tag::Link(..) => {
+                    if links.pop().unwrap_or(false) {
+                        html.push_str("</a>");
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(content) | Event::Html(content) => {
+                escape_html(&content, &mut html);
+                text.push_str(&content);
+            }
+            Event::Code(content) => {
+                html.push_str("<code>");
+                escape_html(&content, &mut html);
+                html.push_str("</code>");
+                text.push_str(&content);
+            }
+            Event::SoftBreak => {
+                html.push('\n');
+                text.push(' ');
+            }
+            Event::HardBreak => {
+                html.push_str("<br>");
+                text.push('\n');
+            }
+            _ => {}
+        }
+    }
+    (html, text.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use rustrict::CensorStr;
+
+    use super::*;
+
+    #[test]
+    fn raw_html_is_escaped() {
+        let (html, text) = render_markdown("say <script>alert(1)</script> *hi*");
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains("<em>hi</em>"));
+        assert!(text.contains("<script>"));
+    }
+
+    #[test]
+    fn unsafe_links_keep_only_their_text() {
+        let (html, text) = render_markdown("[click](javascript:alert(1))");
+        assert!(!html.contains("<a"));
+        assert!(!html.contains("javascript:"));
+        assert_eq!(text, "click");
+        let (html, _) = render_markdown("[site](https://example.com)");
+        assert!(html.contains("<a href=\"https://example.com\""));
+    }
+
+    #[test]
+    fn censor_sees_text_split_by_emphasis() {
+        let formatted = format_message("f*uc*k", MessageFormat::Markdown);
+        assert_eq!(formatted.visible_text, "fuck");
+        assert!(formatted.visible_text.is_inappropriate());
+    }
+
+    #[test]
+    fn plain_messages_are_not_rendered() {
+        let formatted = format_message("**bold**", MessageFormat::Plain);
+        assert_eq!(formatted.visible_text, "**bold**");
+        assert!(formatted.rendered.is_none());
+    }
+}
@@ -2,22 +2,28 @@ use std::sync::Arc;
 
 use axum::{
     extract::State,
-    http::{Request, StatusCode},
+    http::{header, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Extension, Json,
 };
 use rustis::{
     client::Client,
-    commands::{GenericCommands, HashCommands},
+    commands::{GenericCommands, HashCommands, SetCondition, SetExpiration, StringCommands},
 };
 use serde::Serialize;
 
-use crate::{prisma_client::client::users_rooms, shared::arc_clients::State as AppState};
+use crate::{
+    prisma_client::client::{users_rooms, RoomKind},
+    shared::arc_clients::State as AppState,
+    users::handlers::block_user::is_blocked,
+};
 
-enum CanTalkError {
+pub enum CanTalkError {
     Muted,
-    TooFast,
+    Archived,
+    Blocked,
+    TooFast(i64),
     InternalError,
 }
 
@@ -26,15 +32,25 @@ pub struct CanTalkErrorResponse {
     pub success: bool,
     pub http_code: u16,
     pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<i64>,
+}
+
+impl CanTalkError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            CanTalkError::Muted => "Muted",
+            CanTalkError::Archived => "Chat Is Archived",
+            CanTalkError::Blocked => "Conversation Is Blocked",
+            CanTalkError::TooFast(_) => "Sending Messages Too Fast",
+            CanTalkError::InternalError => "Internal Server Error",
+        }
+    }
 }
 
 impl IntoResponse for CanTalkError {
     fn into_response(self) -> axum::response::Response {
-        let error_message: String = match self {
-            CanTalkError::Muted => "Muted".to_string(),
-            CanTalkError::TooFast => "Sending Messages Too Fast".to_string(),
-            CanTalkError::InternalError => "Internal Server Error".to_string(),
-        };
+        let error_message = self.reason().to_string();
         match self {
             CanTalkError::Muted => (
                 StatusCode::BAD_REQUEST,
@@ -42,33 +58,53 @@ impl IntoResponse for CanTalkError {
                     success: false,
                     http_code: 400,
                     error: error_message,
+                    retry_after: None,
+                }),
+            )
+                .into_response(),
+            CanTalkError::Archived | CanTalkError::Blocked => (
+                StatusCode::FORBIDDEN,
+                Json(CanTalkErrorResponse {
+                    success: false,
+                    http_code: 403,
+                    error: error_message,
+                    retry_after: None,
                 }),
-            ),
-            CanTalkError::TooFast => (
+            )
+                .into_response(),
+            CanTalkError::TooFast(retry_after) => (
                 StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
                 Json(CanTalkErrorResponse {
                     success: false,
                     http_code: 429,
                     error: error_message,
+                    retry_after: Some(retry_after),
                 }),
-            ),
+            )
+                .into_response(),
             CanTalkError::InternalError => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(CanTalkErrorResponse {
                     success: false,
                     http_code: 500,
                     error: error_message,
+                    retry_after: None,
                 }),
-            ),
+            )
+                .into_response(),
         }
-        .into_response()
     }
 }
+
+// Returns the seconds left in the window once `limit` messages were sent in it
 pub async fn check_ratelimit(
     redis_client: Arc<Client>,
     user_id: i64,
     chat_id: i64,
-) -> Result<bool, rustis::Error> {
+    limit: i64,
+    window: i64,
+) -> Result<Option<i64>, rustis::Error> {
     let key = format!("ratelimit:{}:{}", user_id, chat_id);
     let command: Option<String> = redis_client.hget(&key, "count").await?;
     match command {
@@ -76,50 +112,173 @@ pub async fn check_ratelimit(
             let count = count.parse::<i64>();
             let count = match count {
                 Ok(count) => count,
-                Err(_) => return Ok(false),
+                Err(_) => return Ok(Some(window)),
             };
-            if count >= 5 {
-                Ok(false)
+            if count >= limit {
+                let ttl = redis_client.ttl(&key).await?;
+                Ok(Some(ttl.max(1)))
             } else {
                 redis_client.hincrby(&key, "count", 1).await?;
-                Ok(true)
+                Ok(None)
             }
         }
         None => {
             redis_client.hincrby(&key, "count", 1).await?;
             let is_set = redis_client
-                .expire(&key, 5, rustis::commands::ExpireOption::None)
+                .expire(
+                    &key,
+                    window.try_into().unwrap_or(5),
+                    rustis::commands::ExpireOption::None,
+                )
                 .await?;
             if !is_set {
                 redis_client.del(&key).await?;
-                return Ok(false);
+                return Ok(Some(window));
             }
-            Ok(true)
+            Ok(None)
         }
     }
 }
+
+pub fn slow_mode_key(user_id: i64, chat_id: i64) -> String {
+    format!("slowmode:{}:{}", user_id, chat_id)
+}
+
+// Slow mode allows a single message per `seconds`, returns the seconds left to wait.
+// The slot is taken with a single SET NX so concurrent sends can't both get it
+pub async fn check_slow_mode(
+    redis_client: Arc<Client>,
+    user_id: i64,
+    chat_id: i64,
+    seconds: u64,
+) -> Result<Option<i64>, rustis::Error> {
+    let key = slow_mode_key(user_id, chat_id);
+    let acquired = redis_client
+        .set_with_options(&key, 1, SetCondition::NX, SetExpiration::Ex(seconds), false)
+        .await?;
+    if acquired {
+        return Ok(None);
+    }
+    let ttl = redis_client.ttl(&key).await?;
+    Ok(Some(ttl.max(1)))
+}
+
+// Checks that hold for every message, the scheduler runs them again at delivery time
+pub async fn check_participant(
+    state: &AppState,
+    participant_room: &users_rooms::Data,
+) -> Result<(), CanTalkError> {
+    if participant_room.muted {
+        return Err(CanTalkError::Muted);
+    }
+    let room = match participant_room.room {
+        Some(ref room) => room,
+        None => return Err(CanTalkError::InternalError),
+    };
+    if room.archived {
+        return Err(CanTalkError::Archived);
+    }
+    // A block on either side closes the direct conversation
+    if room.kind == RoomKind::Direct {
+        let peer = state
+            .prisma_client
+            .users_rooms()
+            .find_first(vec![
+                users_rooms::room_id::equals(participant_room.room_id),
+                users_rooms::user_id::not(participant_room.user_id),
+            ])
+            .exec()
+            .await;
+        let peer = match peer {
+            Ok(Some(peer)) => peer,
+            Ok(None) => return Err(CanTalkError::Blocked),
+            Err(_) => return Err(CanTalkError::InternalError),
+        };
+        match is_blocked(
+            state.prisma_client.clone(),
+            participant_room.user_id,
+            peer.user_id,
+        )
+        .await
+        {
+            Ok(false) => {}
+            Ok(true) => return Err(CanTalkError::Blocked),
+            Err(_) => return Err(CanTalkError::InternalError),
+        };
+    }
+    Ok(())
+}
+
 pub async fn can_talk<B>(
     State(state): State<AppState>,
     Extension(participant_room): Extension<users_rooms::Data>,
     request: Request<B>,
     next: Next<B>,
 ) -> Response {
-    if participant_room.muted {
-        return CanTalkError::Muted.into_response();
+    if let Err(error) = check_participant(&state, &participant_room).await {
+        return error.into_response();
     }
+    let room = match participant_room.room {
+        Some(ref room) => room,
+        None => return CanTalkError::InternalError.into_response(),
+    };
     let rate_limit = check_ratelimit(
-        state.redis_client,
+        state.redis_client.clone(),
         participant_room.user_id.into(),
         participant_room.room_id.into(),
+        room.message_limit.into(),
+        room.message_window.into(),
     )
     .await;
     let rate_limit = match rate_limit {
         Ok(rate_limit) => rate_limit,
         Err(_) => return CanTalkError::InternalError.into_response(),
     };
-    if !rate_limit {
-        return CanTalkError::TooFast.into_response();
+    if let Some(retry_after) = rate_limit {
+        return CanTalkError::TooFast(retry_after).into_response();
+    }
+    // Staff is exempt from slow mode
+    let is_staff = room.user_id == Some(participant_room.user_id);
+    let slow_mode = room.slow_mode > 0 && !is_staff;
+    if slow_mode {
+        let slot = check_slow_mode(
+            state.redis_client.clone(),
+            participant_room.user_id.into(),
+            participant_room.room_id.into(),
+            room.slow_mode as u64,
+        )
+        .await;
+        match slot {
+            Ok(None) => {}
+            Ok(Some(retry_after)) => return CanTalkError::TooFast(retry_after).into_response(),
+            Err(_) => return CanTalkError::InternalError.into_response(),
+        };
     }
 
+    let response = next.run(request).await;
+    // A message the handler refused (invalid body, censored...) gives the slot back
+    if slow_mode && !response.status().is_success() {
+        state
+            .redis_client
+            .del(slow_mode_key(
+                participant_room.user_id.into(),
+                participant_room.room_id.into(),
+            ))
+            .await
+            .ok();
+    }
+    response
+}
+
+// Edits and uploads go through the same checks as new messages, without spending the send limits
+pub async fn can_edit<B>(
+    State(state): State<AppState>,
+    Extension(participant_room): Extension<users_rooms::Data>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if let Err(error) = check_participant(&state, &participant_room).await {
+        return error.into_response();
+    }
     next.run(request).await
 }
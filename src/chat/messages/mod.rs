@@ -1,6 +1,23 @@
+pub mod cancel_scheduled;
 pub mod delete_message;
+pub mod deliver_scheduled;
+pub mod edit_message;
+pub mod edit_scheduled;
 pub mod interfaces;
+pub mod markdown;
 pub mod middlewares;
+pub mod pin_message;
+pub mod process_attachment;
+pub mod react_message;
+pub mod read_messages;
+pub mod retrieve_attachment;
 pub mod retrieve_message;
 pub mod retrieve_messages;
+pub mod retrieve_pinned;
+pub mod retrieve_revisions;
+pub mod retrieve_scheduled;
+pub mod retrieve_thread;
+pub mod schedule_message;
+pub mod search_messages;
 pub mod send_message;
+pub mod upload_attachment;
@@ -0,0 +1,199 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use rustis::commands::PubSubCommands;
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{messages, user, users_rooms},
+    rejection::path::CustomPathDataRejection,
+    shared::{arc_clients::State as AppState, room_limits::ROOM_LIMITS},
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+use super::interfaces::retrieve_message_params::RetrieveSingleMessageParam;
+
+#[derive(Serialize)]
+pub struct PinMessageErrorResponse {
+    pub success: bool,
+    pub http_code: u16,
+    pub error: String,
+}
+
+#[derive(Serialize)]
+pub struct WebsocketPinMessageData {
+    pub message_id: i32,
+    pub action: String,
+}
+
+// Shared with delete_message so clients drop removed pins as well
+pub async fn publish_pin_event(state: &AppState, room_id: i32, message_id: i32, pinned: bool) {
+    let prefix = format!("chat:{}", room_id);
+    state
+        .redis_client
+        .publish(
+            prefix.clone(),
+            serde_json::to_string(&WebSocketMessage {
+                record: Records::Message,
+                queue: prefix,
+                data: serde_json::json!(WebsocketPinMessageData {
+                    message_id,
+                    action: if pinned { "pin" } else { "unpin" }.to_string(),
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+}
+
+async fn set_pinned(
+    state: AppState,
+    participant: users_rooms::Data,
+    message_id: i32,
+    pinned: bool,
+) -> Result<StatusCode, (StatusCode, Json<PinMessageErrorResponse>)> {
+    let message = state
+        .prisma_client
+        .messages()
+        .find_first(vec![
+            messages::id::equals(message_id),
+            messages::room_id::equals(participant.room_id),
+        ])
+        .exec()
+        .await;
+    let message = match message {
+        Ok(Some(message)) => message,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(PinMessageErrorResponse {
+                    success: false,
+                    http_code: 404,
+                    error: "Message not found".to_string(),
+                }),
+            ))
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(PinMessageErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ))
+        }
+    };
+    if message.pinned == pinned {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(PinMessageErrorResponse {
+                success: false,
+                http_code: 409,
+                error: if pinned {
+                    "Message is already pinned".to_string()
+                } else {
+                    "Message is not pinned".to_string()
+                },
+            }),
+        ));
+    }
+    if pinned {
+        let pinned_count = state
+            .prisma_client
+            .messages()
+            .count(vec![
+                messages::room_id::equals(participant.room_id),
+                messages::pinned::equals(true),
+            ])
+            .exec()
+            .await;
+        match pinned_count {
+            Ok(pinned_count) => {
+                if pinned_count >= ROOM_LIMITS.max_pinned {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        Json(PinMessageErrorResponse {
+                            success: false,
+                            http_code: 400,
+                            error: format!(
+                                "A room cannot have more than {} pinned messages",
+                                ROOM_LIMITS.max_pinned
+                            ),
+                        }),
+                    ));
+                }
+            }
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(PinMessageErrorResponse {
+                        success: false,
+                        http_code: 500,
+                        error: "Internal server error".to_string(),
+                    }),
+                ))
+            }
+        };
+    }
+    let changes = if pinned {
+        vec![
+            messages::pinned::set(true),
+            messages::pinned_at::set(Some(chrono::Utc::now().into())),
+            messages::pinned_by::connect(user::UniqueWhereParam::IdEquals(participant.user_id)),
+        ]
+    } else {
+        vec![
+            messages::pinned::set(false),
+            messages::pinned_at::set(None),
+            messages::pinned_by::disconnect(),
+        ]
+    };
+    let update = state
+        .prisma_client
+        .messages()
+        .update(messages::UniqueWhereParam::IdEquals(message.id), changes)
+        .exec()
+        .await;
+    match update {
+        Ok(_) => {}
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(PinMessageErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ))
+        }
+    };
+    publish_pin_event(&state, participant.room_id, message.id, pinned).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn pin_message(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<
+        Path<RetrieveSingleMessageParam>,
+        CustomPathDataRejection,
+    >,
+) -> Result<StatusCode, (StatusCode, Json<PinMessageErrorResponse>)> {
+    set_pinned(state, participant, params.message_id, true).await
+}
+
+pub async fn unpin_message(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<
+        Path<RetrieveSingleMessageParam>,
+        CustomPathDataRejection,
+    >,
+) -> Result<StatusCode, (StatusCode, Json<PinMessageErrorResponse>)> {
+    set_pinned(state, participant, params.message_id, false).await
+}
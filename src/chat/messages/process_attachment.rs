@@ -0,0 +1,397 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::gif::{GifDecoder, GifEncoder, Repeat},
+    error::{LimitError, LimitErrorKind},
+    io::{Limits, Reader},
+    AnimationDecoder, DynamicImage, ImageDecoder, ImageError, ImageOutputFormat,
+};
+use rustis::commands::PubSubCommands;
+use tokio::sync::mpsc::Receiver;
+
+use crate::{
+    prisma_client::client::{attachments, AttachmentState},
+    shared::arc_clients::State as AppState,
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+// Uploads wait for a free slot once this many images are queued
+pub const IMAGE_QUEUE_SIZE: usize = 64;
+// Longest side of a generated thumbnail, in pixels
+const THUMBNAIL_SIZE: u32 = 320;
+// Larger images are refused instead of decoded
+const MAX_DIMENSION: u32 = 8192;
+// Longer animations are refused instead of decoded
+const MAX_GIF_FRAMES: usize = 500;
+// Processing rows older than this are taken as abandoned by a stopped instance
+const STALE_JOB_SECONDS: i64 = 15 * 60;
+// Upper bound on the pixel memory of one image, all frames of an animation together
+const MAX_DECODED_BYTES: u64 = 512 * 1024 * 1024;
+
+pub struct ImageJob {
+    pub attachment_id: String,
+    pub storage_key: String,
+    pub mime_type: String,
+    pub bytes: Vec<u8>,
+}
+
+// The raw upload waits here until processed, so a restart can pick the job up again.
+// Nothing serves this key, the metadata it still carries never reaches other users
+pub fn quarantine_key(storage_key: &str) -> String {
+    format!("{}.raw", storage_key)
+}
+
+pub struct ProcessedImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub thumbnail: Vec<u8>,
+}
+
+pub fn is_processed_image(mime_type: &str) -> bool {
+    matches!(
+        mime_type,
+        "image/png" | "image/jpeg" | "image/gif" | "image/webp"
+    )
+}
+
+// Read before the metadata is dropped, so photos keep the rotation they were taken with
+fn orientation(bytes: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+        .unwrap_or(1)
+}
+
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+fn encode(image: &DynamicImage, format: ImageOutputFormat) -> Result<Vec<u8>, ImageError> {
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, format)?;
+    Ok(bytes.into_inner())
+}
+
+fn decode_limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODED_BYTES);
+    limits
+}
+
+fn too_large() -> ImageError {
+    ImageError::Limits(LimitError::from_kind(LimitErrorKind::InsufficientMemory))
+}
+
+// Only the frames and their delays are written back, application and comment extensions
+// (where XMP and GPS hide in GIFs) are left behind. Every frame is a full RGBA canvas, the
+// decoder limits don't apply to them so their total size is capped here
+fn reencode_gif(bytes: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let mut decoder = GifDecoder::new(Cursor::new(bytes))?;
+    decoder.set_limits(decode_limits())?;
+    let (width, height) = decoder.dimensions();
+    let frame_size = width as u64 * height as u64 * 4;
+    let mut frames = Vec::new();
+    for frame in decoder.into_frames() {
+        if frames.len() == MAX_GIF_FRAMES
+            || (frames.len() as u64 + 1) * frame_size > MAX_DECODED_BYTES
+        {
+            return Err(too_large());
+        }
+        frames.push(frame?);
+    }
+    let mut cleaned = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut cleaned);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(frames)?;
+    }
+    Ok(cleaned)
+}
+
+// Encoding the decoded pixels again drops every metadata block (EXIF with GPS, XMP, text chunks),
+// GIFs keep their frames. WebP is stored as PNG
+pub fn process_image(bytes: &[u8], mime_type: &str) -> Result<ProcessedImage, ImageError> {
+    let mut reader = Reader::new(Cursor::new(bytes)).with_guessed_format()?;
+    reader.limits(decode_limits());
+    let image = apply_orientation(reader.decode()?, orientation(bytes));
+    let (cleaned, mime_type) = match mime_type {
+        "image/gif" => (reencode_gif(bytes)?, "image/gif"),
+        "image/jpeg" => (encode(&image, ImageOutputFormat::Jpeg(90))?, "image/jpeg"),
+        _ => (encode(&image, ImageOutputFormat::Png)?, "image/png"),
+    };
+    let thumbnail = encode(
+        &image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE),
+        ImageOutputFormat::Png,
+    )?;
+    Ok(ProcessedImage {
+        bytes: cleaned,
+        mime_type: mime_type.to_string(),
+        width: image.width(),
+        height: image.height(),
+        thumbnail,
+    })
+}
+
+async fn run_job(state: &AppState, job: ImageJob) -> Option<attachments::Data> {
+    let ImageJob {
+        attachment_id,
+        storage_key,
+        mime_type,
+        bytes,
+    } = job;
+    // Decoding is CPU bound, keep it off the async workers
+    let processed = tokio::task::spawn_blocking(move || process_image(&bytes, &mime_type)).await;
+    let updates = match processed {
+        Ok(Ok(processed)) => {
+            let thumbnail_key = format!("{}.thumb", storage_key);
+            let size = processed.bytes.len() as i32;
+            let stored = match state
+                .storage
+                .put(&storage_key, &processed.mime_type, processed.bytes)
+                .await
+            {
+                Ok(_) => {
+                    state
+                        .storage
+                        .put(&thumbnail_key, "image/png", processed.thumbnail)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match stored {
+                Ok(_) => vec![
+                    attachments::state::set(AttachmentState::Ready),
+                    attachments::mime_type::set(processed.mime_type),
+                    attachments::size::set(size),
+                    attachments::width::set(Some(processed.width as i32)),
+                    attachments::height::set(Some(processed.height as i32)),
+                    attachments::thumbnail_key::set(Some(thumbnail_key)),
+                ],
+                Err(_) => {
+                    state.storage.delete(&storage_key).await.ok();
+                    vec![attachments::state::set(AttachmentState::Failed)]
+                }
+            }
+        }
+        _ => vec![attachments::state::set(AttachmentState::Failed)],
+    };
+    settle_job(state, attachment_id, &storage_key, updates).await
+}
+
+// Guarded on PROCESSING, a job recovered by another instance may have settled it already
+async fn settle_job(
+    state: &AppState,
+    attachment_id: String,
+    storage_key: &str,
+    updates: Vec<attachments::SetParam>,
+) -> Option<attachments::Data> {
+    let settled = state
+        .prisma_client
+        .attachments()
+        .update_many(
+            vec![
+                attachments::id::equals(attachment_id.clone()),
+                attachments::state::equals(AttachmentState::Processing),
+            ],
+            updates,
+        )
+        .exec()
+        .await;
+    if !matches!(settled, Ok(1)) {
+        return None;
+    }
+    state
+        .storage
+        .delete(&quarantine_key(storage_key))
+        .await
+        .ok();
+    state
+        .prisma_client
+        .attachments()
+        .find_unique(attachments::UniqueWhereParam::IdEquals(attachment_id))
+        .exec()
+        .await
+        .ok()
+        .flatten()
+}
+
+// Jobs only live in memory, images left PROCESSING by a stopped server are queued again
+// from their quarantined upload, or failed when it is gone. Recent rows may still be in the
+// queue of another running instance and are left to it
+pub async fn recover_image_jobs(state: &AppState) -> Result<(), prisma_client_rust::QueryError> {
+    let stale_before = chrono::Utc::now() - chrono::Duration::seconds(STALE_JOB_SECONDS);
+    let pending = state
+        .prisma_client
+        .attachments()
+        .find_many(vec![
+            attachments::state::equals(AttachmentState::Processing),
+            attachments::created_at::lt(stale_before.into()),
+        ])
+        .exec()
+        .await?;
+    for attachment in pending {
+        match state
+            .storage
+            .get(&quarantine_key(&attachment.storage_key))
+            .await
+        {
+            Ok(bytes) => {
+                let job = ImageJob {
+                    attachment_id: attachment.id,
+                    storage_key: attachment.storage_key,
+                    mime_type: attachment.mime_type,
+                    bytes,
+                };
+                if state.image_jobs.send(job).await.is_err() {
+                    break;
+                }
+            }
+            Err(_) => {
+                let failed = settle_job(
+                    state,
+                    attachment.id,
+                    &attachment.storage_key,
+                    vec![attachments::state::set(AttachmentState::Failed)],
+                )
+                .await;
+                if let Some(failed) = failed {
+                    publish_attachment_event(state, &failed).await;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Sent attachments update the whole room, unsent ones only concern their uploader
+pub async fn publish_attachment_event(state: &AppState, attachment: &attachments::Data) {
+    let (channel, queue) = match attachment.message_id {
+        Some(_) => (
+            format!("chat:{}", attachment.room_id),
+            format!("chat:{}", attachment.room_id),
+        ),
+        None => (
+            format!("priv_user:{}", attachment.uploader_id),
+            format!("chat-{}", attachment.room_id),
+        ),
+    };
+    state
+        .redis_client
+        .publish(
+            channel,
+            serde_json::to_string(&WebSocketMessage {
+                record: Records::Message,
+                queue,
+                data: serde_json::json!({
+                    "attachment_id": attachment.id,
+                    "message_id": attachment.message_id,
+                    "state": attachment.state,
+                    "width": attachment.width,
+                    "height": attachment.height,
+                    "type": "attachment_processed",
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+}
+
+// Images are handled one at a time, uploads only wait when the queue is full
+pub fn spawn_image_worker(state: AppState, mut jobs: Receiver<ImageJob>) {
+    let recovery_state = state.clone();
+    // Repeated so jobs of an instance that stopped while this one runs are picked up too
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(STALE_JOB_SECONDS as u64));
+        loop {
+            interval.tick().await;
+            if let Err(e) = recover_image_jobs(&recovery_state).await {
+                println!("{:?}", e);
+            }
+        }
+    });
+    tokio::spawn(async move {
+        while let Some(job) = jobs.recv().await {
+            if let Some(attachment) = run_job(&state, job).await {
+                publish_attachment_event(&state, &attachment).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbImage;
+
+    use super::*;
+
+    // Little endian TIFF with IFD0 pointing at a GPS IFD that holds GPSLatitudeRef = "N"
+    fn gps_exif_segment() -> Vec<u8> {
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        // IFD0, one GPSInfo entry pointing at offset 26
+        tiff.extend_from_slice(&[1, 0, 0x25, 0x88, 4, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0, 0, 0, 0]);
+        // GPS IFD, one GPSLatitudeRef entry
+        tiff.extend_from_slice(&[1, 0, 1, 0, 2, 0, 2, 0, 0, 0, b'N', 0, 0, 0, 0, 0, 0, 0]);
+        let length = (2 + 6 + tiff.len()) as u16;
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&length.to_be_bytes());
+        segment.extend_from_slice(b"Exif\0\0");
+        segment.extend_from_slice(&tiff);
+        segment
+    }
+
+    fn jpeg_with_gps() -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(16, 8, image::Rgb([200, 40, 40])));
+        let jpeg = encode(&image, ImageOutputFormat::Jpeg(90)).unwrap();
+        // The segment goes right after the SOI marker
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend_from_slice(&gps_exif_segment());
+        bytes.extend_from_slice(&jpeg[2..]);
+        bytes
+    }
+
+    #[test]
+    fn gps_metadata_is_dropped() {
+        let bytes = jpeg_with_gps();
+        let exif = exif::Reader::new()
+            .read_from_container(&mut Cursor::new(&bytes))
+            .unwrap();
+        assert!(exif
+            .get_field(exif::Tag::GPSLatitudeRef, exif::In::PRIMARY)
+            .is_some());
+
+        let processed = process_image(&bytes, "image/jpeg").unwrap();
+        assert_eq!(processed.mime_type, "image/jpeg");
+        assert_eq!((processed.width, processed.height), (16, 8));
+        assert!(exif::Reader::new()
+            .read_from_container(&mut Cursor::new(&processed.bytes))
+            .is_err());
+    }
+
+    #[test]
+    fn png_keeps_its_format_and_gets_a_thumbnail() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+        let png = encode(&image, ImageOutputFormat::Png).unwrap();
+        let processed = process_image(&png, "image/png").unwrap();
+        assert_eq!(processed.mime_type, "image/png");
+        assert!(!processed.thumbnail.is_empty());
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use prisma_client_rust::prisma_errors::query_engine::UniqueKeyViolation;
+use rustis::commands::PubSubCommands;
+use serde::Serialize;
+use validator::Validate;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{messages, reactions, user, users_rooms},
+    rejection::path::CustomPathDataRejection,
+    shared::{arc_clients::State as AppState, room_limits::ROOM_LIMITS},
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+use super::interfaces::reaction_params::ReactionParams;
+
+#[derive(Serialize)]
+pub struct ReactionResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct WebsocketReactionData {
+    pub message_id: i32,
+    pub user_id: i32,
+    pub emoji: String,
+    pub count: i64,
+    pub action: String,
+}
+
+async fn set_reaction(
+    state: AppState,
+    participant: users_rooms::Data,
+    params: ReactionParams,
+    reacted: bool,
+) -> (StatusCode, Json<ReactionResponse>) {
+    match params.validate() {
+        Ok(_) => {
+            if participant.room.as_ref().unwrap().archived {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(ReactionResponse {
+                        success: false,
+                        http_code: 403,
+                        count: None,
+                        validation_errors: None,
+                        error: Some("Chat is archived".to_string()),
+                    }),
+                );
+            }
+            let message = state
+                .prisma_client
+                .messages()
+                .find_first(vec![
+                    messages::id::equals(params.message_id),
+                    messages::room_id::equals(participant.room_id),
+                ])
+                .exec()
+                .await;
+            let message = match message {
+                Ok(Some(message)) => message,
+                Ok(None) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(ReactionResponse {
+                            success: false,
+                            http_code: 404,
+                            count: None,
+                            validation_errors: None,
+                            error: Some("Message not found".to_string()),
+                        }),
+                    )
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ReactionResponse {
+                            success: false,
+                            http_code: 500,
+                            count: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let existing_reactions = state
+                .prisma_client
+                .reactions()
+                .find_many(vec![reactions::message_id::equals(message.id)])
+                .exec()
+                .await;
+            let existing_reactions = match existing_reactions {
+                Ok(existing_reactions) => existing_reactions,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ReactionResponse {
+                            success: false,
+                            http_code: 500,
+                            count: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let has_reacted = existing_reactions.iter().any(|reaction| {
+                reaction.user_id == participant.user_id && reaction.emoji == params.emoji
+            });
+            if has_reacted == reacted {
+                return if reacted {
+                    (
+                        StatusCode::CONFLICT,
+                        Json(ReactionResponse {
+                            success: false,
+                            http_code: 409,
+                            count: None,
+                            validation_errors: None,
+                            error: Some("You already reacted with this emoji".to_string()),
+                        }),
+                    )
+                } else {
+                    (
+                        StatusCode::NOT_FOUND,
+                        Json(ReactionResponse {
+                            success: false,
+                            http_code: 404,
+                            count: None,
+                            validation_errors: None,
+                            error: Some("Reaction not found".to_string()),
+                        }),
+                    )
+                };
+            }
+            if reacted {
+                // New emoji count against the cap, piling onto an existing one does not
+                let distinct_emoji = existing_reactions
+                    .iter()
+                    .map(|reaction| reaction.emoji.as_str())
+                    .collect::<HashSet<&str>>();
+                if !distinct_emoji.contains(params.emoji.as_str())
+                    && distinct_emoji.len() >= ROOM_LIMITS.max_distinct_reactions
+                {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        Json(ReactionResponse {
+                            success: false,
+                            http_code: 400,
+                            count: None,
+                            validation_errors: None,
+                            error: Some("Too many different reactions on this message".to_string()),
+                        }),
+                    );
+                }
+                let reaction = state
+                    .prisma_client
+                    .reactions()
+                    .create(
+                        params.emoji.clone(),
+                        user::UniqueWhereParam::IdEquals(participant.user_id),
+                        messages::UniqueWhereParam::IdEquals(message.id),
+                        vec![],
+                    )
+                    .exec()
+                    .await;
+                match reaction {
+                    Ok(_) => {}
+                    // A concurrent request from the same user got there first
+                    Err(e) if e.is_prisma_error::<UniqueKeyViolation>() => {
+                        return (
+                            StatusCode::CONFLICT,
+                            Json(ReactionResponse {
+                                success: false,
+                                http_code: 409,
+                                count: None,
+                                validation_errors: None,
+                                error: Some("You already reacted with this emoji".to_string()),
+                            }),
+                        )
+                    }
+                    Err(_) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ReactionResponse {
+                                success: false,
+                                http_code: 500,
+                                count: None,
+                                validation_errors: None,
+                                error: Some("Internal server error".to_string()),
+                            }),
+                        )
+                    }
+                };
+                // Concurrent first uses of different emoji can all pass the check above, the
+                // one that went over the cap takes its reaction back
+                if !distinct_emoji.contains(params.emoji.as_str()) {
+                    let emoji_after = state
+                        .prisma_client
+                        .reactions()
+                        .find_many(vec![reactions::message_id::equals(message.id)])
+                        .exec()
+                        .await
+                        .unwrap_or_default();
+                    let distinct_after = emoji_after
+                        .iter()
+                        .map(|reaction| reaction.emoji.as_str())
+                        .collect::<HashSet<&str>>();
+                    if distinct_after.len() > ROOM_LIMITS.max_distinct_reactions {
+                        state
+                            .prisma_client
+                            .reactions()
+                            .delete_many(vec![
+                                reactions::user_id::equals(participant.user_id),
+                                reactions::message_id::equals(message.id),
+                                reactions::emoji::equals(params.emoji.clone()),
+                            ])
+                            .exec()
+                            .await
+                            .ok();
+                        return (
+                            StatusCode::BAD_REQUEST,
+                            Json(ReactionResponse {
+                                success: false,
+                                http_code: 400,
+                                count: None,
+                                validation_errors: None,
+                                error: Some(
+                                    "Too many different reactions on this message".to_string(),
+                                ),
+                            }),
+                        );
+                    }
+                }
+            } else {
+                let reaction = state
+                    .prisma_client
+                    .reactions()
+                    .delete_many(vec![
+                        reactions::user_id::equals(participant.user_id),
+                        reactions::message_id::equals(message.id),
+                        reactions::emoji::equals(params.emoji.clone()),
+                    ])
+                    .exec()
+                    .await;
+                match reaction {
+                    Ok(0) => {
+                        // Removed by a concurrent request in the meantime
+                        return (
+                            StatusCode::NOT_FOUND,
+                            Json(ReactionResponse {
+                                success: false,
+                                http_code: 404,
+                                count: None,
+                                validation_errors: None,
+                                error: Some("Reaction not found".to_string()),
+                            }),
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(_) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ReactionResponse {
+                                success: false,
+                                http_code: 500,
+                                count: None,
+                                validation_errors: None,
+                                error: Some("Internal server error".to_string()),
+                            }),
+                        )
+                    }
+                };
+            }
+            // Counted after the write, other users may have reacted since the first read
+            let count = state
+                .prisma_client
+                .reactions()
+                .count(vec![
+                    reactions::message_id::equals(message.id),
+                    reactions::emoji::equals(params.emoji.clone()),
+                ])
+                .exec()
+                .await;
+            let count = match count {
+                Ok(count) => count,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ReactionResponse {
+                            success: false,
+                            http_code: 500,
+                            count: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let prefix = format!("chat:{}", participant.room_id);
+            state
+                .redis_client
+                .publish(
+                    prefix.clone(),
+                    serde_json::to_string(&WebSocketMessage {
+                        record: Records::Message,
+                        queue: prefix,
+                        data: serde_json::json!(WebsocketReactionData {
+                            message_id: message.id,
+                            user_id: participant.user_id,
+                            emoji: params.emoji,
+                            count,
+                            action: if reacted {
+                                "reaction_add"
+                            } else {
+                                "reaction_remove"
+                            }
+                            .to_string(),
+                        }),
+                    })
+                    .unwrap(),
+                )
+                .await
+                .ok();
+            (
+                StatusCode::OK,
+                Json(ReactionResponse {
+                    success: true,
+                    http_code: 200,
+                    count: Some(count),
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ReactionResponse {
+                    success: false,
+                    http_code: 422,
+                    count: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
+
+pub async fn add_reaction(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<Path<ReactionParams>, CustomPathDataRejection>,
+) -> (StatusCode, Json<ReactionResponse>) {
+    set_reaction(state, participant, params, true).await
+}
+
+pub async fn remove_reaction(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<Path<ReactionParams>, CustomPathDataRejection>,
+) -> (StatusCode, Json<ReactionResponse>) {
+    set_reaction(state, participant, params, false).await
+}
@@ -0,0 +1,387 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Json as ExtractJson, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use prisma_client_rust::{operator::or, QueryError};
+use rustis::{
+    client::{BatchPreparedCommand, Client},
+    commands::{CallBuilder, HashCommands, ScriptingCommands},
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{mentions, messages, users_rooms, PrismaClient},
+    rejection::json::CustomJsonDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+// Per user hashes of room id -> count, so room badges never scan `Messages`
+pub fn unread_key(user_id: i32) -> String {
+    format!("unread:{}", user_id)
+}
+
+pub fn unread_mentions_key(user_id: i32) -> String {
+    format!("unread_mentions:{}", user_id)
+}
+
+// Only counters that were seeded are bumped, HINCRBY alone would recreate a dropped field at 1
+// and the unread listing would trust it instead of counting again
+const BUMP_SEEDED_SCRIPT: &str = r#"
+if redis.call("HEXISTS", KEYS[1], ARGV[1]) == 1 then
+    return redis.call("HINCRBY", KEYS[1], ARGV[1], 1)
+end
+return 0
+"#;
+
+// Called once a message is stored, everyone but the author gets a new unread message
+pub async fn bump_unread(
+    state: &AppState,
+    message: &messages::Data,
+    mentioned_ids: &[i32],
+) -> Result<(), rustis::Error> {
+    let participants = state
+        .prisma_client
+        .users_rooms()
+        .find_many(vec![
+            users_rooms::room_id::equals(message.room_id),
+            users_rooms::user_id::not(message.user_id),
+        ])
+        .exec()
+        .await
+        .unwrap_or_default();
+    // One round trip for the whole room instead of one per participant
+    let mut pipeline = state.redis_client.create_pipeline();
+    for participant in &participants {
+        pipeline
+            .eval::<()>(
+                CallBuilder::script(BUMP_SEEDED_SCRIPT)
+                    .keys(unread_key(participant.user_id))
+                    .args(message.room_id.to_string()),
+            )
+            .forget();
+    }
+    for mentioned_id in mentioned_ids {
+        pipeline
+            .eval::<()>(
+                CallBuilder::script(BUMP_SEEDED_SCRIPT)
+                    .keys(unread_mentions_key(*mentioned_id))
+                    .args(message.room_id.to_string()),
+            )
+            .forget();
+    }
+    // Executing an empty pipeline panics in rustis
+    if participants.is_empty() && mentioned_ids.is_empty() {
+        return Ok(());
+    }
+    pipeline.execute::<()>().await?;
+    Ok(())
+}
+
+// Drops the counters of a room the user is no longer part of
+pub async fn clear_unread(
+    redis_client: Arc<Client>,
+    user_id: i32,
+    room_id: i32,
+) -> Result<(), rustis::Error> {
+    redis_client
+        .hdel(unread_key(user_id), room_id.to_string())
+        .await?;
+    redis_client
+        .hdel(unread_mentions_key(user_id), room_id.to_string())
+        .await?;
+    Ok(())
+}
+
+// Deleted messages may still be counted as unread, dropping the room field makes the next
+// unread listing seed it again from the database
+pub async fn invalidate_unread(
+    state: &AppState,
+    room_id: i32,
+    newest_deleted_id: i32,
+) -> Result<(), rustis::Error> {
+    let participants = state
+        .prisma_client
+        .users_rooms()
+        .find_many(vec![
+            users_rooms::room_id::equals(room_id),
+            or(vec![
+                users_rooms::last_read_id::equals(None),
+                users_rooms::last_read_id::lt(newest_deleted_id),
+            ]),
+        ])
+        .exec()
+        .await
+        .unwrap_or_default();
+    if participants.is_empty() {
+        return Ok(());
+    }
+    let mut pipeline = state.redis_client.create_pipeline();
+    for participant in &participants {
+        pipeline
+            .hdel(unread_key(participant.user_id), room_id.to_string())
+            .forget();
+        pipeline
+            .hdel(
+                unread_mentions_key(participant.user_id),
+                room_id.to_string(),
+            )
+            .forget();
+    }
+    pipeline.execute::<()>().await
+}
+
+// The source of truth behind the counters, used to (re)seed them
+pub async fn count_unread(
+    prisma_client: Arc<PrismaClient>,
+    participant: &users_rooms::Data,
+) -> Result<(i64, i64), QueryError> {
+    let unread = prisma_client
+        .messages()
+        .count(vec![
+            messages::room_id::equals(participant.room_id),
+            messages::user_id::not(participant.user_id),
+            messages::id::gt(participant.last_read_id.unwrap_or(0)),
+            messages::created_at::gte(participant.created_at),
+        ])
+        .exec()
+        .await?;
+    let unread_mentions = prisma_client
+        .mentions()
+        .count(vec![
+            mentions::user_id::equals(participant.user_id),
+            mentions::room_id::equals(participant.room_id),
+            mentions::read::equals(false),
+        ])
+        .exec()
+        .await?;
+    Ok((unread, unread_mentions))
+}
+
+pub async fn store_unread(
+    redis_client: Arc<Client>,
+    participant: &users_rooms::Data,
+    (unread, unread_mentions): (i64, i64),
+) -> Result<(), rustis::Error> {
+    redis_client
+        .hset(
+            unread_key(participant.user_id),
+            [(participant.room_id.to_string(), unread)],
+        )
+        .await?;
+    redis_client
+        .hset(
+            unread_mentions_key(participant.user_id),
+            [(participant.room_id.to_string(), unread_mentions)],
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ReadMessagesBody {
+    #[validate(
+        required(message = "message_id is required"),
+        range(min = 1, message = "Message id invalid")
+    )]
+    pub message_id: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct ReadMessagesResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_read_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unread: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mentions: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn read_messages(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<ReadMessagesBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<ReadMessagesResponse>) {
+    match body.validate() {
+        Ok(_) => {
+            let message_id = body.message_id.unwrap();
+            let message = state
+                .prisma_client
+                .messages()
+                .find_first(vec![
+                    messages::id::equals(message_id),
+                    messages::room_id::equals(participant.room_id),
+                ])
+                .exec()
+                .await;
+            match message {
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(ReadMessagesResponse {
+                            success: false,
+                            http_code: 404,
+                            last_read_id: None,
+                            unread: None,
+                            mentions: None,
+                            validation_errors: None,
+                            error: Some("Message not found".to_string()),
+                        }),
+                    )
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ReadMessagesResponse {
+                            success: false,
+                            http_code: 500,
+                            last_read_id: None,
+                            unread: None,
+                            mentions: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            // The marker only moves forward, late requests from another device are ignored
+            let last_read_id = participant.last_read_id.unwrap_or(0).max(message_id);
+            let participant = state
+                .prisma_client
+                .users_rooms()
+                .update(
+                    users_rooms::UniqueWhereParam::IdEquals(participant.id),
+                    vec![users_rooms::last_read_id::set(Some(last_read_id))],
+                )
+                .exec()
+                .await;
+            let participant = match participant {
+                Ok(participant) => participant,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ReadMessagesResponse {
+                            success: false,
+                            http_code: 500,
+                            last_read_id: None,
+                            unread: None,
+                            mentions: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let mentions_read = state
+                .prisma_client
+                .mentions()
+                .update_many(
+                    vec![
+                        mentions::user_id::equals(participant.user_id),
+                        mentions::room_id::equals(participant.room_id),
+                        mentions::message_id::lte(last_read_id),
+                        mentions::read::equals(false),
+                    ],
+                    vec![
+                        mentions::read::set(true),
+                        mentions::read_at::set(Some(chrono::Utc::now().into())),
+                    ],
+                )
+                .exec()
+                .await;
+            if mentions_read.is_err() {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ReadMessagesResponse {
+                        success: false,
+                        http_code: 500,
+                        last_read_id: None,
+                        unread: None,
+                        mentions: None,
+                        validation_errors: None,
+                        error: Some("Internal server error".to_string()),
+                    }),
+                );
+            }
+            let counts = count_unread(state.prisma_client.clone(), &participant).await;
+            let counts = match counts {
+                Ok(counts) => counts,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ReadMessagesResponse {
+                            success: false,
+                            http_code: 500,
+                            last_read_id: None,
+                            unread: None,
+                            mentions: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            store_unread(state.redis_client.clone(), &participant, counts)
+                .await
+                .ok();
+            (
+                StatusCode::OK,
+                Json(ReadMessagesResponse {
+                    success: true,
+                    http_code: 200,
+                    last_read_id: Some(last_read_id),
+                    unread: Some(counts.0),
+                    mentions: Some(counts.1),
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ReadMessagesResponse {
+                    success: false,
+                    http_code: 422,
+                    last_read_id: None,
+                    unread: None,
+                    mentions: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use prisma_client_rust::operator::or;
+use serde::Serialize;
+use validator::Validate;
+
+use crate::{
+    prisma_client::client::{attachments, users_rooms, AttachmentState},
+    rejection::{path::CustomPathDataRejection, query::CustomQueryDataRejection},
+    shared::{arc_clients::State as AppState, storage::StorageError},
+};
+
+use super::interfaces::attachment_params::{AttachmentParams, AttachmentQuery};
+
+// Header values only take visible ASCII, other names get an ASCII fallback plus the RFC 5987
+// `filename*` form that browsers prefer
+pub fn content_disposition(file_name: &str) -> String {
+    let fallback = file_name
+        .chars()
+        .map(|c| {
+            if (c.is_ascii_graphic() && c != '"' && c != '\\') || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if fallback == file_name {
+        return format!("attachment; filename=\"{}\"", fallback);
+    }
+    let encoded = file_name
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect::<String>();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback, encoded
+    )
+}
+
+#[derive(Serialize)]
+pub struct RetrieveAttachmentErrorResponse {
+    pub success: bool,
+    pub http_code: u16,
+    pub error: String,
+}
+
+pub async fn retrieve_attachment(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<Path<AttachmentParams>, CustomPathDataRejection>,
+    WithRejection(Query(query), _): WithRejection<Query<AttachmentQuery>, CustomQueryDataRejection>,
+) -> Result<Response, (StatusCode, Json<RetrieveAttachmentErrorResponse>)> {
+    if params.validate().is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(RetrieveAttachmentErrorResponse {
+                success: false,
+                http_code: 400,
+                error: "Attachment id invalid".to_string(),
+            }),
+        ));
+    }
+    // Unsent uploads are only visible to their uploader
+    let attachment = state
+        .prisma_client
+        .attachments()
+        .find_first(vec![
+            attachments::id::equals(params.attachment_id),
+            attachments::room_id::equals(participant.room_id),
+            or(vec![
+                attachments::message_id::not(None),
+                attachments::uploader_id::equals(participant.user_id),
+            ]),
+        ])
+        .exec()
+        .await;
+    let attachment = match attachment {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(RetrieveAttachmentErrorResponse {
+                    success: false,
+                    http_code: 404,
+                    error: "Attachment not found".to_string(),
+                }),
+            ))
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RetrieveAttachmentErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ))
+        }
+    };
+    match attachment.state {
+        AttachmentState::Ready => {}
+        AttachmentState::Processing => {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(RetrieveAttachmentErrorResponse {
+                    success: false,
+                    http_code: 409,
+                    error: "Attachment is still being processed".to_string(),
+                }),
+            ))
+        }
+        AttachmentState::Failed => {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(RetrieveAttachmentErrorResponse {
+                    success: false,
+                    http_code: 422,
+                    error: "Attachment could not be processed".to_string(),
+                }),
+            ))
+        }
+    };
+    let (storage_key, mime_type) = if query.thumbnail.unwrap_or(false) {
+        match attachment.thumbnail_key {
+            Some(thumbnail_key) => (thumbnail_key, "image/png".to_string()),
+            None => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    Json(RetrieveAttachmentErrorResponse {
+                        success: false,
+                        http_code: 404,
+                        error: "Attachment has no thumbnail".to_string(),
+                    }),
+                ))
+            }
+        }
+    } else {
+        (attachment.storage_key, attachment.mime_type)
+    };
+    let bytes = match state.storage.get(&storage_key).await {
+        Ok(bytes) => bytes,
+        Err(StorageError::NotFound) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(RetrieveAttachmentErrorResponse {
+                    success: false,
+                    http_code: 404,
+                    error: "Attachment not found".to_string(),
+                }),
+            ))
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RetrieveAttachmentErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ))
+        }
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, mime_type),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&attachment.file_name),
+            ),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        bytes,
+    )
+        .into_response())
+}
@@ -44,6 +44,7 @@ pub async fn retrieve_message(
                 .messages()
                 .find_unique(messages::UniqueWhereParam::IdEquals(message_id))
                 .with(messages::user::fetch())
+                .with(messages::attachments::fetch(vec![]))
                 .exec()
                 .await;
             let message = match message {
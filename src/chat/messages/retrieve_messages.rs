@@ -1,23 +1,24 @@
-use std::collections::HashSet;
-
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Extension, Json,
 };
 use axum_extra::extract::WithRejection;
 use prisma_client_rust::QueryError;
 use serde::Serialize;
-use validator::Validate;
+use validator::{Validate, ValidationErrors};
 
 use crate::{
     error::validation_error::ValidationError,
-    prisma_client::client::{messages, user, users_rooms},
-    rejection::path::CustomPathDataRejection,
+    prisma_client::client::{messages, reactions, users_rooms, MessageFormat},
+    rejection::{path::CustomPathDataRejection, query::CustomQueryDataRejection},
     shared::arc_clients::State as AppState,
 };
 
-use super::interfaces::retrieve_message_params::RetrieveMessageParams;
+use super::{
+    interfaces::retrieve_message_params::{HistoryQuery, RetrieveMessageParams},
+    upload_attachment::AttachmentSummary,
+};
 
 #[derive(Serialize)]
 pub struct Sender {
@@ -25,11 +26,55 @@ pub struct Sender {
     pub username: String,
 }
 
+#[derive(Serialize)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+    // Whether the requesting user is one of the reactors
+    pub reacted: bool,
+}
+
+// Folds raw reaction rows into per emoji counts, in order of first use
+pub fn count_reactions(reactions: &[reactions::Data], user_id: i32) -> Vec<ReactionCount> {
+    let mut counts: Vec<ReactionCount> = Vec::new();
+    for reaction in reactions {
+        match counts
+            .iter_mut()
+            .find(|count| count.emoji == reaction.emoji)
+        {
+            Some(count) => {
+                count.count += 1;
+                count.reacted |= reaction.user_id == user_id;
+            }
+            None => counts.push(ReactionCount {
+                emoji: reaction.emoji.clone(),
+                count: 1,
+                reacted: reaction.user_id == user_id,
+            }),
+        }
+    }
+    counts
+}
+
 #[derive(Serialize)]
 pub struct MessageReponse {
     pub message_id: i32,
     pub message: String,
+    pub format: MessageFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rendered: Option<String>,
     pub sender: Sender,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i32>,
+    pub reply_count: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_reply_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reactions: Vec<ReactionCount>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<AttachmentSummary>,
 }
 
 impl From<messages::Data> for MessageReponse {
@@ -38,28 +83,358 @@ impl From<messages::Data> for MessageReponse {
         Self {
             message_id: value.id,
             message: value.message,
+            format: value.format,
+            rendered: value.rendered,
             sender: Sender {
                 id: user.id,
                 username: user.username,
             },
+            parent_id: value.parent_id,
+            reply_count: value.reply_count,
+            last_reply_at: value
+                .last_reply_at
+                .map(|last_reply_at| last_reply_at.into()),
+            edited_at: value.edited_at.map(|edited_at| edited_at.into()),
+            // Filled in by the handlers that load reactions
+            reactions: Vec::new(),
+            attachments: value
+                .attachments
+                .unwrap_or_default()
+                .into_iter()
+                .map(AttachmentSummary::from)
+                .collect(),
         }
     }
 }
 
+pub enum HistoryCursor {
+    Latest,
+    Before(i32),
+    After(i32),
+    Around(i32),
+}
+
 #[derive(Serialize)]
 pub struct MessagesResponse {
     pub success: bool,
     pub http_code: u16,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub messages: Option<Vec<MessageReponse>>,
+    // Same as `before_cursor`, kept for the `/:limit/:message_id` route
     #[serde(skip_serializing_if = "Option::is_none")]
     pub latest_id: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_cursor: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_cursor: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more_before: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more_after: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub validation_errors: Option<Vec<ValidationError>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
 
+// Newest first
+struct HistoryPage {
+    messages: Vec<messages::Data>,
+    has_more_before: bool,
+    has_more_after: bool,
+}
+
+async fn fetch_history(
+    state: &AppState,
+    room_id: i32,
+    cursor: Vec<messages::WhereParam>,
+    direction: prisma_client_rust::Direction,
+    take: i64,
+) -> Result<Vec<messages::Data>, QueryError> {
+    let mut filters = vec![
+        messages::room_id::equals(room_id),
+        // Replies are read through their thread
+        messages::parent_id::equals(None),
+    ];
+    filters.extend(cursor);
+    state
+        .prisma_client
+        .messages()
+        .find_many(filters)
+        .with(messages::user::fetch())
+        .with(messages::attachments::fetch(vec![]))
+        .order_by(messages::id::order(direction))
+        .take(take)
+        .exec()
+        .await
+}
+
+async fn has_history(
+    state: &AppState,
+    room_id: i32,
+    cursor: messages::WhereParam,
+) -> Result<bool, QueryError> {
+    let count = state
+        .prisma_client
+        .messages()
+        .count(vec![
+            messages::room_id::equals(room_id),
+            messages::parent_id::equals(None),
+            cursor,
+        ])
+        .exec()
+        .await?;
+    Ok(count > 0)
+}
+
+// Ordered by id, which never ties the way `createdAt` does. Each side fetches one extra
+// row to tell whether more messages exist past the page. `None` when the jump target is missing
+async fn load_history(
+    state: &AppState,
+    room_id: i32,
+    cursor: HistoryCursor,
+    limit: i64,
+) -> Result<Option<HistoryPage>, QueryError> {
+    use prisma_client_rust::Direction;
+    let page = match cursor {
+        HistoryCursor::Latest => {
+            let mut older =
+                fetch_history(state, room_id, vec![], Direction::Desc, limit + 1).await?;
+            let has_more_before = older.len() as i64 > limit;
+            older.truncate(limit as usize);
+            HistoryPage {
+                messages: older,
+                has_more_before,
+                has_more_after: false,
+            }
+        }
+        HistoryCursor::Before(message_id) => {
+            let mut older = fetch_history(
+                state,
+                room_id,
+                vec![messages::id::lt(message_id)],
+                Direction::Desc,
+                limit + 1,
+            )
+            .await?;
+            let has_more_before = older.len() as i64 > limit;
+            older.truncate(limit as usize);
+            HistoryPage {
+                messages: older,
+                has_more_before,
+                has_more_after: has_history(state, room_id, messages::id::gte(message_id)).await?,
+            }
+        }
+        HistoryCursor::After(message_id) => {
+            let mut newer = fetch_history(
+                state,
+                room_id,
+                vec![messages::id::gt(message_id)],
+                Direction::Asc,
+                limit + 1,
+            )
+            .await?;
+            let has_more_after = newer.len() as i64 > limit;
+            newer.truncate(limit as usize);
+            newer.reverse();
+            HistoryPage {
+                messages: newer,
+                has_more_before: has_history(state, room_id, messages::id::lte(message_id)).await?,
+                has_more_after,
+            }
+        }
+        HistoryCursor::Around(message_id) => {
+            let target = fetch_history(
+                state,
+                room_id,
+                vec![messages::id::equals(message_id)],
+                Direction::Desc,
+                1,
+            )
+            .await?;
+            let target = match target.into_iter().next() {
+                Some(target) => target,
+                None => return Ok(None),
+            };
+            let older_take = (limit - 1) / 2;
+            let newer_take = limit - 1 - older_take;
+            let mut older = fetch_history(
+                state,
+                room_id,
+                vec![messages::id::lt(message_id)],
+                Direction::Desc,
+                older_take + 1,
+            )
+            .await?;
+            let mut newer = fetch_history(
+                state,
+                room_id,
+                vec![messages::id::gt(message_id)],
+                Direction::Asc,
+                newer_take + 1,
+            )
+            .await?;
+            let has_more_before = older.len() as i64 > older_take;
+            let has_more_after = newer.len() as i64 > newer_take;
+            older.truncate(older_take as usize);
+            newer.truncate(newer_take as usize);
+            newer.reverse();
+            newer.push(target);
+            newer.extend(older);
+            HistoryPage {
+                messages: newer,
+                has_more_before,
+                has_more_after,
+            }
+        }
+    };
+    Ok(Some(page))
+}
+
+async fn history_response(
+    state: AppState,
+    participant: users_rooms::Data,
+    cursor: HistoryCursor,
+    limit: i64,
+) -> (StatusCode, Json<MessagesResponse>) {
+    let page = load_history(&state, participant.room_id, cursor, limit).await;
+    let page = match page {
+        Ok(Some(page)) => page,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(MessagesResponse {
+                    success: false,
+                    http_code: 404,
+                    messages: None,
+                    latest_id: None,
+                    before_cursor: None,
+                    after_cursor: None,
+                    has_more_before: None,
+                    has_more_after: None,
+                    validation_errors: None,
+                    error: Some("Message not found".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(MessagesResponse {
+                    success: false,
+                    http_code: 500,
+                    messages: None,
+                    latest_id: None,
+                    before_cursor: None,
+                    after_cursor: None,
+                    has_more_before: None,
+                    has_more_after: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let reactions = state
+        .prisma_client
+        .reactions()
+        .find_many(vec![reactions::message_id::in_vec(
+            page.messages.iter().map(|m| m.id).collect(),
+        )])
+        .order_by(reactions::id::order(prisma_client_rust::Direction::Asc))
+        .exec()
+        .await;
+    let reactions = match reactions {
+        Ok(reactions) => reactions,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(MessagesResponse {
+                    success: false,
+                    http_code: 500,
+                    messages: None,
+                    latest_id: None,
+                    before_cursor: None,
+                    after_cursor: None,
+                    has_more_before: None,
+                    has_more_after: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let before_cursor = page.messages.last().map(|m| m.id);
+    let after_cursor = page.messages.first().map(|m| m.id);
+    let messages = page
+        .messages
+        .into_iter()
+        .map(|m| {
+            let message_reactions = reactions
+                .iter()
+                .filter(|reaction| reaction.message_id == m.id)
+                .cloned()
+                .collect::<Vec<reactions::Data>>();
+            let mut message = MessageReponse::from(m);
+            message.reactions = count_reactions(&message_reactions, participant.user_id);
+            message
+        })
+        .collect::<Vec<MessageReponse>>();
+    // An empty page is a valid answer, clients stop paging on the has_more flags
+    (
+        StatusCode::OK,
+        Json(MessagesResponse {
+            success: true,
+            http_code: 200,
+            messages: Some(messages),
+            latest_id: before_cursor,
+            before_cursor,
+            after_cursor,
+            has_more_before: Some(page.has_more_before),
+            has_more_after: Some(page.has_more_after),
+            validation_errors: None,
+            error: None,
+        }),
+    )
+}
+
+fn validation_response(
+    validation_errors: ValidationErrors,
+) -> (StatusCode, Json<MessagesResponse>) {
+    let validation_errors = validation_errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| ValidationError {
+            field: field.to_string(),
+            // Message is a cow
+            messages: errors
+                .iter()
+                .map(|e| {
+                    e.message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| "Unknown error".to_string())
+                })
+                .collect(),
+        });
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(MessagesResponse {
+            success: false,
+            http_code: 422,
+            messages: None,
+            latest_id: None,
+            before_cursor: None,
+            after_cursor: None,
+            has_more_before: None,
+            has_more_after: None,
+            validation_errors: Some(validation_errors.collect()),
+            error: None,
+        }),
+    )
+}
+
+// A message id of 0 returns the latest messages, any other id the messages before it
 pub async fn retrieve_messages(
     State(state): State<AppState>,
     Extension(participant): Extension<users_rooms::Data>,
@@ -70,156 +445,80 @@ pub async fn retrieve_messages(
 ) -> (StatusCode, Json<MessagesResponse>) {
     match params.validate() {
         Ok(_) => {
-            let (limit, message_id) = (params.limit, params.message_id);
-            let messages: Result<Vec<messages::Data>, QueryError>;
-
-            if message_id == 0 {
-                // Init curso
-                messages = state
-                    .prisma_client
-                    .messages()
-                    .find_many(vec![messages::room_id::equals(participant.room_id)])
-                    .order_by(messages::OrderByParam::CreatedAt(
-                        prisma_client_rust::Direction::Desc,
-                    ))
-                    .take(limit as i64)
-                    .exec()
-                    .await;
-            } else {
-                messages = state
-                    .prisma_client
-                    .messages()
-                    .find_many(vec![messages::room_id::equals(participant.room_id)])
-                    .skip(1)
-                    .cursor(messages::id::equals(message_id))
-                    .take(limit as i64)
-                    .order_by(messages::OrderByParam::CreatedAt(
-                        prisma_client_rust::Direction::Desc,
-                    ))
-                    .exec()
-                    .await
-            }
-            let messages = match messages {
-                Ok(messages) => {
-                    if messages.len() == 0 {
-                        return (
-                            StatusCode::NOT_FOUND,
-                            Json(MessagesResponse {
-                                success: false,
-                                http_code: 404,
-                                error: Some("No messages found".to_string()),
-                                latest_id: None,
-                                validation_errors: None,
-                                messages: None,
-                            }),
-                        );
-                    }
-                    messages
-                }
-                Err(_) => {
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(MessagesResponse {
-                            success: false,
-                            http_code: 500,
-                            error: Some("Internal server error".to_string()),
-                            latest_id: None,
-                            validation_errors: None,
-                            messages: None,
-                        }),
-                    )
-                }
+            let cursor = match params.message_id {
+                0 => HistoryCursor::Latest,
+                message_id => HistoryCursor::Before(message_id),
             };
+            history_response(state, participant, cursor, params.limit as i64).await
+        }
+        Err(validation_errors) => validation_response(validation_errors),
+    }
+}
 
-            let mut unique_user_ids = HashSet::new();
-            for message in &messages {
-                unique_user_ids.insert(message.user_id);
-            }
-
-            let users = state
-                .prisma_client
-                .user()
-                .find_many(vec![user::id::in_vec(
-                    unique_user_ids.into_iter().collect(),
-                )])
-                .exec()
-                .await;
-            let users = match users {
-                Ok(users) => {
-                    // Box every data
-                    let users = users
-                        .into_iter()
-                        .map(|u| Box::new(u))
-                        .collect::<Vec<Box<user::Data>>>();
-                    users
-                }
-                Err(_) => {
+pub async fn retrieve_history(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Query(query), _): WithRejection<Query<HistoryQuery>, CustomQueryDataRejection>,
+) -> (StatusCode, Json<MessagesResponse>) {
+    match query.validate() {
+        Ok(_) => {
+            let cursor = match (query.before, query.after, query.around) {
+                (None, None, None) => HistoryCursor::Latest,
+                (Some(before), None, None) => HistoryCursor::Before(before),
+                (None, Some(after), None) => HistoryCursor::After(after),
+                (None, None, Some(around)) => HistoryCursor::Around(around),
+                _ => {
                     return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
+                        StatusCode::BAD_REQUEST,
                         Json(MessagesResponse {
                             success: false,
-                            http_code: 500,
-                            error: Some("Internal server error".to_string()),
+                            http_code: 400,
+                            messages: None,
                             latest_id: None,
+                            before_cursor: None,
+                            after_cursor: None,
+                            has_more_before: None,
+                            has_more_after: None,
                             validation_errors: None,
-                            messages: None,
+                            error: Some("Use only one of before, after or around".to_string()),
                         }),
                     )
                 }
             };
-            // Put user into messages
-
-            let latest_id = messages.iter().last().unwrap().id;
-            let messages = messages
-                .into_iter()
-                .filter_map(|mut m| {
-                    let user = users.iter().find(|u| u.id == m.user_id);
-                    m.user = user.cloned();
-                    Some(m)
-                })
-                .map(|m| MessageReponse::from(m))
-                .collect::<Vec<MessageReponse>>();
-            return (
-                StatusCode::OK,
-                Json(MessagesResponse {
-                    success: true,
-                    http_code: 200,
-                    messages: Some(messages),
-                    latest_id: Some(latest_id),
-                    validation_errors: None,
-                    error: None,
-                }),
-            );
+            history_response(state, participant, cursor, query.limit.unwrap_or(25) as i64).await
         }
-        Err(validation_errors) => {
-            let validation_errors =
-                validation_errors
-                    .field_errors()
-                    .into_iter()
-                    .map(|(field, errors)| ValidationError {
-                        field: field.to_string(),
-                        // Message is a cow
-                        messages: errors
-                            .iter()
-                            .map(|e| {
-                                e.message
-                                    .as_ref()
-                                    .map(|m| m.to_string())
-                                    .unwrap_or_else(|| "Unknown error".to_string())
-                            })
-                            .collect(),
-                    });
-            return (
-                StatusCode::UNPROCESSABLE_ENTITY,
-                Json(MessagesResponse {
-                    success: false,
-                    http_code: 422,
-                    validation_errors: Some(validation_errors.collect()),
-                    latest_id: None,
-                    error: None,
-                    messages: None,
-                }),
-            );
+        Err(validation_errors) => validation_response(validation_errors),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reaction(user_id: i32, emoji: &str) -> reactions::Data {
+        reactions::Data {
+            id: 0,
+            created_at: chrono::Utc::now().into(),
+            emoji: emoji.to_string(),
+            user: None,
+            user_id,
+            message: None,
+            message_id: 1,
         }
     }
+
+    #[test]
+    fn reactions_are_grouped_by_emoji() {
+        let counts = count_reactions(
+            &[reaction(1, "👍"), reaction(2, "👍"), reaction(2, "🎉")],
+            1,
+        );
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[0].emoji, "👍");
+        assert_eq!(counts[0].count, 2);
+        assert!(counts[0].reacted);
+        assert_eq!(counts[1].emoji, "🎉");
+        assert_eq!(counts[1].count, 1);
+        assert!(!counts[1].reacted);
+    }
 }
@@ -0,0 +1,90 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{messages, users_rooms},
+    shared::arc_clients::State as AppState,
+};
+
+use super::retrieve_messages::Sender;
+
+#[derive(Serialize)]
+pub struct PinnedMessage {
+    pub message_id: i32,
+    pub message: String,
+    pub sender: Sender,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_by: Option<Sender>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<messages::Data> for PinnedMessage {
+    fn from(value: messages::Data) -> Self {
+        let user = value.user.unwrap();
+        Self {
+            message_id: value.id,
+            message: value.message,
+            sender: Sender {
+                id: user.id,
+                username: user.username,
+            },
+            pinned_by: value.pinned_by.flatten().map(|pinned_by| Sender {
+                id: pinned_by.id,
+                username: pinned_by.username,
+            }),
+            pinned_at: value.pinned_at.map(|pinned_at| pinned_at.into()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PinnedMessagesResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<PinnedMessage>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn retrieve_pinned(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+) -> (StatusCode, Json<PinnedMessagesResponse>) {
+    // The pin cap keeps this list small enough to return in one go
+    let messages = state
+        .prisma_client
+        .messages()
+        .find_many(vec![
+            messages::room_id::equals(participant.room_id),
+            messages::pinned::equals(true),
+        ])
+        .order_by(messages::pinned_at::order(
+            prisma_client_rust::Direction::Desc,
+        ))
+        .with(messages::user::fetch())
+        .with(messages::pinned_by::fetch())
+        .exec()
+        .await;
+    match messages {
+        Ok(messages) => (
+            StatusCode::OK,
+            Json(PinnedMessagesResponse {
+                success: true,
+                http_code: 200,
+                messages: Some(messages.into_iter().map(PinnedMessage::from).collect()),
+                error: None,
+            }),
+        ),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(PinnedMessagesResponse {
+                success: false,
+                http_code: 500,
+                messages: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        ),
+    }
+}
@@ -0,0 +1,107 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{message_revisions, messages, users_rooms},
+    rejection::path::CustomPathDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+use super::interfaces::retrieve_message_params::RetrieveSingleMessageParam;
+
+#[derive(Serialize)]
+pub struct MessageRevision {
+    pub id: i32,
+    pub content: String,
+    pub replaced_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<message_revisions::Data> for MessageRevision {
+    fn from(value: message_revisions::Data) -> Self {
+        Self {
+            id: value.id,
+            content: value.content,
+            replaced_at: value.created_at.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RetrieveRevisionsResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revisions: Option<Vec<MessageRevision>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn retrieve_revisions(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<
+        Path<RetrieveSingleMessageParam>,
+        CustomPathDataRejection,
+    >,
+) -> (StatusCode, Json<RetrieveRevisionsResponse>) {
+    let message = state
+        .prisma_client
+        .messages()
+        .find_first(vec![
+            messages::id::equals(params.message_id),
+            messages::room_id::equals(participant.room_id),
+        ])
+        .with(
+            messages::revisions::fetch(vec![]).order_by(message_revisions::id::order(
+                prisma_client_rust::Direction::Asc,
+            )),
+        )
+        .exec()
+        .await;
+    match message {
+        Ok(Some(message)) => (
+            StatusCode::OK,
+            Json(RetrieveRevisionsResponse {
+                success: true,
+                http_code: 200,
+                current: Some(message.message),
+                revisions: Some(
+                    message
+                        .revisions
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(MessageRevision::from)
+                        .collect(),
+                ),
+                error: None,
+            }),
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(RetrieveRevisionsResponse {
+                success: false,
+                http_code: 404,
+                current: None,
+                revisions: None,
+                error: Some("Message not found".to_string()),
+            }),
+        ),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(RetrieveRevisionsResponse {
+                success: false,
+                http_code: 500,
+                current: None,
+                revisions: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        ),
+    }
+}
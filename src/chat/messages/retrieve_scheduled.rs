@@ -0,0 +1,65 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{scheduled_messages, users_rooms, ScheduledState},
+    shared::arc_clients::State as AppState,
+};
+
+use super::schedule_message::ScheduledMessageSummary;
+
+#[derive(Serialize)]
+pub struct ScheduledMessagesResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<Vec<ScheduledMessageSummary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// The participant's own messages that were not delivered yet, failed ones stay listed
+// until they are rescheduled or cancelled
+pub async fn retrieve_scheduled(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+) -> (StatusCode, Json<ScheduledMessagesResponse>) {
+    let scheduled = state
+        .prisma_client
+        .scheduled_messages()
+        .find_many(vec![
+            scheduled_messages::user_id::equals(participant.user_id),
+            scheduled_messages::room_id::equals(participant.room_id),
+            scheduled_messages::state::not(ScheduledState::Sent),
+        ])
+        .order_by(scheduled_messages::send_at::order(
+            prisma_client_rust::Direction::Asc,
+        ))
+        .exec()
+        .await;
+    match scheduled {
+        Ok(scheduled) => (
+            StatusCode::OK,
+            Json(ScheduledMessagesResponse {
+                success: true,
+                http_code: 200,
+                scheduled: Some(
+                    scheduled
+                        .into_iter()
+                        .map(ScheduledMessageSummary::from)
+                        .collect(),
+                ),
+                error: None,
+            }),
+        ),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ScheduledMessagesResponse {
+                success: false,
+                http_code: 500,
+                scheduled: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        ),
+    }
+}
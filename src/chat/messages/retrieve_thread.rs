@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use prisma_client_rust::QueryError;
+use rustis::commands::PubSubCommands;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::{validate_all, ValidationError},
+    prisma_client::client::{messages, users_rooms, PrismaClient},
+    rejection::{path::CustomPathDataRejection, query::CustomQueryDataRejection},
+    shared::arc_clients::State as AppState,
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+use super::{
+    interfaces::retrieve_message_params::RetrieveSingleMessageParam,
+    retrieve_messages::MessageReponse,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct ThreadQuery {
+    // Id of the last reply already seen, replies come oldest first
+    #[validate(range(min = 1, message = "cursor must be greater than 0"))]
+    pub cursor: Option<i32>,
+    #[validate(range(min = 1, max = 50, message = "limit must be between 1 and 50"))]
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ThreadResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<MessageReponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replies: Option<Vec<MessageReponse>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct WebsocketThreadData {
+    pub action: String,
+    pub parent_id: i32,
+    pub message_id: i32,
+    pub reply_count: i32,
+    pub last_reply_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Recounts the replies so the badge stays right after creations and deletions
+pub async fn refresh_thread(
+    prisma_client: Arc<PrismaClient>,
+    parent_id: i32,
+) -> Result<messages::Data, QueryError> {
+    let reply_count = prisma_client
+        .messages()
+        .count(vec![messages::parent_id::equals(Some(parent_id))])
+        .exec()
+        .await?;
+    let last_reply = prisma_client
+        .messages()
+        .find_first(vec![messages::parent_id::equals(Some(parent_id))])
+        .order_by(messages::created_at::order(
+            prisma_client_rust::Direction::Desc,
+        ))
+        .exec()
+        .await?;
+    prisma_client
+        .messages()
+        .update(
+            messages::UniqueWhereParam::IdEquals(parent_id),
+            vec![
+                messages::reply_count::set(reply_count as i32),
+                messages::last_reply_at::set(last_reply.map(|reply| reply.created_at)),
+            ],
+        )
+        .exec()
+        .await
+}
+
+// Lets clients update thread badges without refetching the room
+pub async fn publish_thread_event(state: &AppState, parent: &messages::Data, message_id: i32) {
+    let prefix = format!("chat:{}", parent.room_id);
+    state
+        .redis_client
+        .publish(
+            prefix.clone(),
+            serde_json::to_string(&WebSocketMessage {
+                record: Records::Message,
+                queue: prefix,
+                data: serde_json::json!(WebsocketThreadData {
+                    action: "thread".to_string(),
+                    parent_id: parent.id,
+                    message_id,
+                    reply_count: parent.reply_count,
+                    last_reply_at: parent
+                        .last_reply_at
+                        .map(|last_reply_at| last_reply_at.into()),
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+}
+
+pub async fn retrieve_thread(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<
+        Path<RetrieveSingleMessageParam>,
+        CustomPathDataRejection,
+    >,
+    WithRejection(Query(query), _): WithRejection<Query<ThreadQuery>, CustomQueryDataRejection>,
+) -> (StatusCode, Json<ThreadResponse>) {
+    match validate_all(&params, &query) {
+        Ok(_) => {
+            let parent = state
+                .prisma_client
+                .messages()
+                .find_first(vec![
+                    messages::id::equals(params.message_id),
+                    messages::room_id::equals(participant.room_id),
+                    messages::parent_id::equals(None),
+                ])
+                .with(messages::user::fetch())
+                .with(messages::attachments::fetch(vec![]))
+                .exec()
+                .await;
+            let parent = match parent {
+                Ok(Some(parent)) => parent,
+                Ok(None) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(ThreadResponse {
+                            success: false,
+                            http_code: 404,
+                            parent: None,
+                            replies: None,
+                            next_cursor: None,
+                            validation_errors: None,
+                            error: Some("Thread not found".to_string()),
+                        }),
+                    )
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ThreadResponse {
+                            success: false,
+                            http_code: 500,
+                            parent: None,
+                            replies: None,
+                            next_cursor: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let limit = query.limit.unwrap_or(25);
+            let replies = state
+                .prisma_client
+                .messages()
+                .find_many(vec![messages::parent_id::equals(Some(parent.id))])
+                .order_by(messages::id::order(prisma_client_rust::Direction::Asc))
+                .with(messages::user::fetch())
+                .with(messages::attachments::fetch(vec![]));
+            let replies = match query.cursor {
+                Some(cursor) => replies.cursor(messages::id::equals(cursor)).skip(1),
+                None => replies,
+            };
+            let replies = replies.take(limit).exec().await;
+            let replies = match replies {
+                Ok(replies) => replies,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ThreadResponse {
+                            success: false,
+                            http_code: 500,
+                            parent: None,
+                            replies: None,
+                            next_cursor: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            // A full page means there may be more replies after the last one
+            let next_cursor = if replies.len() as i64 == limit {
+                replies.last().map(|reply| reply.id)
+            } else {
+                None
+            };
+            (
+                StatusCode::OK,
+                Json(ThreadResponse {
+                    success: true,
+                    http_code: 200,
+                    parent: Some(parent.into()),
+                    replies: Some(replies.into_iter().map(MessageReponse::from).collect()),
+                    next_cursor,
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ThreadResponse {
+                    success: false,
+                    http_code: 422,
+                    parent: None,
+                    replies: None,
+                    next_cursor: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
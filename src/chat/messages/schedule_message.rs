@@ -0,0 +1,270 @@
+use axum::{
+    extract::{Json as ExtractJson, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationErrors};
+
+use rustrict::CensorStr;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{
+        rooms, scheduled_messages, user, users_rooms, MessageFormat, ScheduledState,
+    },
+    rejection::json::CustomJsonDataRejection,
+    shared::{arc_clients::State as AppState, scheduled_messages::SCHEDULED_MESSAGES},
+};
+
+use super::{markdown::format_message, send_message::find_thread_parent};
+
+#[derive(Deserialize, Validate)]
+pub struct ScheduleMessageBody {
+    #[validate(
+        required(message = "message is required"),
+        length(
+            min = 1,
+            max = 1000,
+            message = "message must be between 1 and 1000 characters"
+        )
+    )]
+    pub message: Option<String>,
+    #[validate(range(min = 1, message = "parent_id invalid"))]
+    pub parent_id: Option<i32>,
+    pub format: Option<MessageFormat>,
+    #[validate(required(message = "send_at is required"))]
+    pub send_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ScheduledMessageSummary {
+    pub id: i32,
+    pub message: String,
+    pub format: MessageFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i32>,
+    pub send_at: chrono::DateTime<chrono::Utc>,
+    pub state: ScheduledState,
+    // The delivered message, once sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+impl From<scheduled_messages::Data> for ScheduledMessageSummary {
+    fn from(value: scheduled_messages::Data) -> Self {
+        Self {
+            id: value.id,
+            message: value.message,
+            format: value.format,
+            parent_id: value.parent_id,
+            send_at: value.send_at.into(),
+            state: value.state,
+            message_id: value.sent_message_id,
+            failure: value.failure,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ScheduledMessageResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<ScheduledMessageSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub fn scheduled_validation_response(
+    validation_errors: ValidationErrors,
+) -> (StatusCode, Json<ScheduledMessageResponse>) {
+    let validation_errors = validation_errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| ValidationError {
+            field: field.to_string(),
+            // Message is a cow
+            messages: errors
+                .iter()
+                .map(|e| {
+                    e.message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| "Unknown error".to_string())
+                })
+                .collect(),
+        });
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ScheduledMessageResponse {
+            success: false,
+            http_code: 400,
+            scheduled: None,
+            validation_errors: Some(validation_errors.collect()),
+            error: None,
+        }),
+    )
+}
+
+pub fn invalid_send_at() -> (StatusCode, Json<ScheduledMessageResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ScheduledMessageResponse {
+            success: false,
+            http_code: 400,
+            scheduled: None,
+            validation_errors: None,
+            error: Some(format!(
+                "send_at must be in the future and at most {} days ahead",
+                SCHEDULED_MESSAGES.max_days_ahead
+            )),
+        }),
+    )
+}
+
+pub async fn schedule_message(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<ScheduleMessageBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<ScheduledMessageResponse>) {
+    if let Err(validation_errors) = body.validate() {
+        return scheduled_validation_response(validation_errors);
+    }
+    let message = body.message.unwrap();
+    let send_at = body.send_at.unwrap();
+    let format = body.format.unwrap_or(MessageFormat::Plain);
+    if !SCHEDULED_MESSAGES.is_valid_send_at(send_at) {
+        return invalid_send_at();
+    }
+    // Rendering happens at delivery, only the visible text is checked now
+    if format_message(&message, format)
+        .visible_text
+        .is_inappropriate()
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ScheduledMessageResponse {
+                success: false,
+                http_code: 400,
+                scheduled: None,
+                validation_errors: None,
+                error: Some("Message is inappropriate".to_string()),
+            }),
+        );
+    }
+    if let Some(parent_id) = body.parent_id {
+        match find_thread_parent(&state, participant.room_id, parent_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(ScheduledMessageResponse {
+                        success: false,
+                        http_code: 404,
+                        scheduled: None,
+                        validation_errors: None,
+                        error: Some("Parent message not found".to_string()),
+                    }),
+                )
+            }
+            Err(_) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ScheduledMessageResponse {
+                        success: false,
+                        http_code: 500,
+                        scheduled: None,
+                        validation_errors: None,
+                        error: Some("Internal server error".to_string()),
+                    }),
+                )
+            }
+        };
+    }
+    let pending_count = state
+        .prisma_client
+        .scheduled_messages()
+        .count(vec![
+            scheduled_messages::user_id::equals(participant.user_id),
+            scheduled_messages::room_id::equals(participant.room_id),
+            scheduled_messages::state::equals(ScheduledState::Pending),
+        ])
+        .exec()
+        .await;
+    match pending_count {
+        Ok(pending_count) if pending_count >= SCHEDULED_MESSAGES.max_pending => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ScheduledMessageResponse {
+                    success: false,
+                    http_code: 400,
+                    scheduled: None,
+                    validation_errors: None,
+                    error: Some(format!(
+                        "A room cannot have more than {} scheduled messages per user",
+                        SCHEDULED_MESSAGES.max_pending
+                    )),
+                }),
+            )
+        }
+        Ok(_) => {}
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ScheduledMessageResponse {
+                    success: false,
+                    http_code: 500,
+                    scheduled: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let scheduled = state
+        .prisma_client
+        .scheduled_messages()
+        .create(
+            message,
+            send_at.into(),
+            user::UniqueWhereParam::IdEquals(participant.user_id),
+            rooms::UniqueWhereParam::IdEquals(participant.room_id),
+            vec![
+                scheduled_messages::format::set(format),
+                scheduled_messages::parent_id::set(body.parent_id),
+            ],
+        )
+        .exec()
+        .await;
+    match scheduled {
+        Ok(scheduled) => (
+            StatusCode::CREATED,
+            Json(ScheduledMessageResponse {
+                success: true,
+                http_code: 201,
+                scheduled: Some(ScheduledMessageSummary::from(scheduled)),
+                validation_errors: None,
+                error: None,
+            }),
+        ),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ScheduledMessageResponse {
+                success: false,
+                http_code: 500,
+                scheduled: None,
+                validation_errors: None,
+                error: Some("Failed to schedule message".to_string()),
+            }),
+        ),
+    }
+}
@@ -0,0 +1,363 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use prisma_client_rust::{PrismaValue, QueryError, Raw};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{messages, user, users_rooms},
+    rejection::query::CustomQueryDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+use super::retrieve_messages::MessageReponse;
+
+// Extra words are ignored, keeps the boolean query small
+const MAX_SEARCH_TERMS: usize = 10;
+
+#[derive(Deserialize, Validate)]
+pub struct SearchQuery {
+    #[validate(
+        required(message = "q is required"),
+        length(min = 1, max = 100, message = "q must be between 1 and 100 characters")
+    )]
+    pub q: Option<String>,
+    #[validate(range(min = 1, message = "sender_id invalid"))]
+    pub sender_id: Option<i32>,
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    // Only messages mentioning someone
+    pub has_mention: Option<bool>,
+    #[validate(range(min = 1, message = "page must be greater than 0"))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 50, message = "limit must be between 1 and 50"))]
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct Highlight {
+    // Character offsets into the message, end exclusive
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize)]
+pub struct SearchHit {
+    pub chat_id: i32,
+    pub message: MessageReponse,
+    pub highlights: Vec<Highlight>,
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<Vec<SearchHit>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchRow {
+    id: i32,
+}
+
+// Splits the query into plain words, so no boolean mode operator from the user reaches MySQL
+pub fn search_terms(q: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for term in q.split(|c: char| !c.is_alphanumeric()) {
+        let term = term.to_lowercase();
+        if !term.is_empty() && !terms.contains(&term) {
+            terms.push(term);
+        }
+        if terms.len() == MAX_SEARCH_TERMS {
+            break;
+        }
+    }
+    terms
+}
+
+// Every word starting with a search term, the same prefix match the index runs
+pub fn highlight(message: &str, terms: &[String]) -> Vec<Highlight> {
+    let chars: Vec<char> = message.chars().collect();
+    let mut highlights = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        if !chars[start].is_alphanumeric() {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < chars.len() && chars[end].is_alphanumeric() {
+            end += 1;
+        }
+        let word = chars[start..end].iter().collect::<String>().to_lowercase();
+        if terms.iter().any(|term| word.starts_with(term.as_str())) {
+            highlights.push(Highlight { start, end });
+        }
+        start = end;
+    }
+    highlights
+}
+
+// Runs the FULLTEXT match restricted to the given rooms, best matches first
+async fn run_search(
+    state: &AppState,
+    room_ids: &[i32],
+    terms: &[String],
+    query: &SearchQuery,
+    skip: i64,
+    take: i64,
+) -> Result<Vec<messages::Data>, QueryError> {
+    if room_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let against = terms
+        .iter()
+        .map(|term| format!("+{}*", term))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let mut sql = format!(
+        "SELECT m.id FROM Messages m WHERE m.roomId IN ({}) \
+         AND MATCH(m.message) AGAINST ({{}} IN BOOLEAN MODE)",
+        vec!["{}"; room_ids.len()].join(", ")
+    );
+    let mut params: Vec<PrismaValue> = room_ids
+        .iter()
+        .map(|room_id| PrismaValue::Int(*room_id as i64))
+        .collect();
+    params.push(PrismaValue::String(against.clone()));
+    if let Some(sender_id) = query.sender_id {
+        sql.push_str(" AND m.userId = {}");
+        params.push(PrismaValue::Int(sender_id as i64));
+    }
+    if let Some(from) = query.from {
+        sql.push_str(" AND m.createdAt >= {}");
+        params.push(PrismaValue::DateTime(from.into()));
+    }
+    if let Some(to) = query.to {
+        sql.push_str(" AND m.createdAt <= {}");
+        params.push(PrismaValue::DateTime(to.into()));
+    }
+    if query.has_mention.unwrap_or(false) {
+        sql.push_str(" AND EXISTS (SELECT 1 FROM Mentions mt WHERE mt.messageId = m.id)");
+    }
+    sql.push_str(
+        " ORDER BY MATCH(m.message) AGAINST ({} IN BOOLEAN MODE) DESC, m.id DESC LIMIT {} OFFSET {}",
+    );
+    params.push(PrismaValue::String(against));
+    params.push(PrismaValue::Int(take));
+    params.push(PrismaValue::Int(skip));
+    let rows: Vec<SearchRow> = state
+        .prisma_client
+        ._query_raw(Raw::new(&sql, params))
+        .exec()
+        .await?;
+    let ids: Vec<i32> = rows.into_iter().map(|row| row.id).collect();
+    let mut found = state
+        .prisma_client
+        .messages()
+        .find_many(vec![messages::id::in_vec(ids.clone())])
+        .with(messages::user::fetch())
+        .with(messages::attachments::fetch(vec![]))
+        .exec()
+        .await?;
+    // Keep the relevance order of the raw query
+    found.sort_by_key(|message| ids.iter().position(|id| *id == message.id));
+    Ok(found)
+}
+
+async fn search(
+    state: AppState,
+    room_ids: Vec<i32>,
+    query: SearchQuery,
+) -> (StatusCode, Json<SearchResponse>) {
+    match query.validate() {
+        Ok(_) => {
+            let terms = search_terms(query.q.as_deref().unwrap_or_default());
+            if terms.is_empty() {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(SearchResponse {
+                        success: false,
+                        http_code: 400,
+                        results: None,
+                        page: None,
+                        has_more: None,
+                        validation_errors: None,
+                        error: Some("q has no searchable words".to_string()),
+                    }),
+                );
+            }
+            let page = query.page.unwrap_or(1);
+            let limit = query.limit.unwrap_or(25);
+            // One extra row tells whether another page exists
+            let found = run_search(
+                &state,
+                &room_ids,
+                &terms,
+                &query,
+                (page - 1).saturating_mul(limit),
+                limit + 1,
+            )
+            .await;
+            let mut found = match found {
+                Ok(found) => found,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(SearchResponse {
+                            success: false,
+                            http_code: 500,
+                            results: None,
+                            page: None,
+                            has_more: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let has_more = found.len() as i64 > limit;
+            found.truncate(limit as usize);
+            let results = found
+                .into_iter()
+                .map(|message| SearchHit {
+                    chat_id: message.room_id,
+                    highlights: highlight(&message.message, &terms),
+                    message: MessageReponse::from(message),
+                })
+                .collect();
+            (
+                StatusCode::OK,
+                Json(SearchResponse {
+                    success: true,
+                    http_code: 200,
+                    results: Some(results),
+                    page: Some(page),
+                    has_more: Some(has_more),
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(SearchResponse {
+                    success: false,
+                    http_code: 422,
+                    results: None,
+                    page: None,
+                    has_more: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
+
+pub async fn search_messages(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Query(query), _): WithRejection<Query<SearchQuery>, CustomQueryDataRejection>,
+) -> (StatusCode, Json<SearchResponse>) {
+    search(state, vec![participant.room_id], query).await
+}
+
+// Searches every room the user currently takes part in
+pub async fn search_all_messages(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Query(query), _): WithRejection<Query<SearchQuery>, CustomQueryDataRejection>,
+) -> (StatusCode, Json<SearchResponse>) {
+    let participations = state
+        .prisma_client
+        .users_rooms()
+        .find_many(vec![users_rooms::user_id::equals(user.id)])
+        .exec()
+        .await;
+    let room_ids = match participations {
+        Ok(participations) => participations
+            .into_iter()
+            .map(|participation| participation.room_id)
+            .collect(),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SearchResponse {
+                    success: false,
+                    http_code: 500,
+                    results: None,
+                    page: None,
+                    has_more: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    search(state, room_ids, query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terms_are_lowercased_and_unique() {
+        assert_eq!(
+            search_terms("Hello, hello WORLD!"),
+            vec!["hello".to_string(), "world".to_string()]
+        );
+        assert!(search_terms(" -- ").is_empty());
+    }
+
+    #[test]
+    fn terms_are_capped() {
+        let q = (0..20)
+            .map(|i| format!("w{}", i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(search_terms(&q).len(), MAX_SEARCH_TERMS);
+    }
+
+    #[test]
+    fn highlights_cover_prefix_matches_in_characters() {
+        let highlights = highlight("Éclair and eclairs", &["ecl".to_string()]);
+        assert_eq!(highlights.len(), 1);
+        assert_eq!((highlights[0].start, highlights[0].end), (11, 18));
+        let highlights = highlight("Éclair", &["écl".to_string()]);
+        assert_eq!((highlights[0].start, highlights[0].end), (0, 6));
+    }
+}
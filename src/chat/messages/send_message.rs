@@ -4,6 +4,7 @@ use axum::{
     Extension, Json,
 };
 use axum_extra::extract::WithRejection;
+use prisma_client_rust::QueryError;
 use rustis::commands::PubSubCommands;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
@@ -12,12 +13,20 @@ use rustrict::CensorStr;
 
 use crate::{
     error::validation_error::ValidationError,
-    prisma_client::client::{rooms, user, users_rooms},
+    prisma_client::client::{
+        attachments, messages, rooms, user, users_rooms, AttachmentState, MessageFormat,
+    },
     rejection::json::CustomJsonDataRejection,
     shared::arc_clients::State as AppState,
     socket::interfaces::websocket_message::WebSocketMessage,
 };
 
+use super::{
+    markdown::{format_message, mention_text},
+    read_messages::bump_unread,
+    retrieve_thread::{publish_thread_event, refresh_thread},
+};
+
 #[derive(Deserialize, Validate)]
 pub struct SendMessageBody {
     #[validate(
@@ -29,6 +38,164 @@ pub struct SendMessageBody {
         )
     )]
     pub message: Option<String>,
+    // Set to reply in the thread of a message
+    #[validate(range(min = 1, message = "parent_id invalid"))]
+    pub parent_id: Option<i32>,
+    // Uploads of this room that were not sent yet
+    #[validate(length(max = 10, message = "A message can carry at most 10 attachments"))]
+    pub attachment_ids: Option<Vec<String>>,
+    // Plain text unless set to MARKDOWN
+    pub format: Option<MessageFormat>,
+}
+
+// Upper bound on the users a single message can notify
+const MAX_MENTIONS: usize = 10;
+
+// Picks the `@username` tokens out of a message, an @ inside a word (emails) is ignored
+pub fn parse_mentions(message: &str) -> Vec<String> {
+    let mut usernames: Vec<String> = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '@' && !previous.map_or(false, |p| p.is_alphanumeric()) {
+            let mut username = String::new();
+            while let Some(&next) = chars.peek() {
+                if next.is_alphanumeric() || next == '_' || next == '-' || next == '.' {
+                    username.push(next);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let username = username.trim_end_matches('.').to_string();
+            if (3..=32).contains(&username.chars().count()) && !usernames.contains(&username) {
+                usernames.push(username);
+            }
+            if usernames.len() == MAX_MENTIONS {
+                break;
+            }
+            previous = None;
+            continue;
+        }
+        previous = Some(c);
+    }
+    usernames
+}
+
+// Stores the mentions of a message and pings every mentioned participant privately,
+// so they hear about it without following the room's queue
+pub async fn record_mentions(state: &AppState, message: &messages::Data) -> Vec<i32> {
+    let usernames = parse_mentions(&mention_text(&message.message, message.format));
+    if usernames.is_empty() {
+        return vec![];
+    }
+    let mentioned = state
+        .prisma_client
+        .user()
+        .find_many(vec![
+            user::username::in_vec(usernames),
+            user::id::not(message.user_id),
+            user::users_rooms::some(vec![users_rooms::room_id::equals(message.room_id)]),
+        ])
+        .exec()
+        .await
+        .unwrap_or_default();
+    let mut mentioned_ids = vec![];
+    for mentioned_user in mentioned {
+        let mention = state
+            .prisma_client
+            .mentions()
+            .create(
+                user::UniqueWhereParam::IdEquals(mentioned_user.id),
+                messages::UniqueWhereParam::IdEquals(message.id),
+                rooms::UniqueWhereParam::IdEquals(message.room_id),
+                vec![],
+            )
+            .exec()
+            .await;
+        let mention = match mention {
+            Ok(mention) => mention,
+            Err(_) => continue,
+        };
+        state
+            .redis_client
+            .publish(
+                format!("priv_user:{}", mentioned_user.id),
+                serde_json::to_string(&WebSocketMessage {
+                    record: crate::socket::interfaces::websocket_message::Records::Message,
+                    queue: format!("chat-{}", message.room_id),
+                    data: serde_json::json!({
+                        "mention_id": mention.id,
+                        "message_id": message.id,
+                        "chat_id": message.room_id,
+                        "author_id": message.user_id,
+                        "type": "mention",
+                    }),
+                })
+                .unwrap(),
+            )
+            .await
+            .ok();
+        mentioned_ids.push(mentioned_user.id);
+    }
+    mentioned_ids
+}
+
+enum CreateMessageError {
+    Query(QueryError),
+    // Another message was sent with one of the attachments first
+    AttachmentsTaken,
+}
+
+impl From<QueryError> for CreateMessageError {
+    fn from(value: QueryError) -> Self {
+        CreateMessageError::Query(value)
+    }
+}
+
+// Only top level messages of the room can hold a thread
+pub async fn find_thread_parent(
+    state: &AppState,
+    room_id: i32,
+    parent_id: i32,
+) -> Result<Option<messages::Data>, QueryError> {
+    state
+        .prisma_client
+        .messages()
+        .find_first(vec![
+            messages::id::equals(parent_id),
+            messages::room_id::equals(room_id),
+            messages::parent_id::equals(None),
+        ])
+        .exec()
+        .await
+}
+
+// Everything that follows a stored message, shared with the scheduled message delivery
+pub async fn publish_message(state: &AppState, message: &messages::Data) {
+    state
+        .redis_client
+        .publish(
+            format!("chat:{}", message.room_id),
+            serde_json::to_string(&WebSocketMessage {
+                record: crate::socket::interfaces::websocket_message::Records::Message,
+                queue: format!("chat:{}", message.room_id),
+                data: serde_json::json!({
+                    "message_id": message.id,
+                    "parent_id": message.parent_id,
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+    if let Some(parent_id) = message.parent_id {
+        if let Ok(parent) = refresh_thread(state.prisma_client.clone(), parent_id).await {
+            publish_thread_event(state, &parent, message.id).await;
+        }
+    }
+    let mentioned_ids = record_mentions(state, message).await;
+    bump_unread(state, message, &mentioned_ids).await.ok();
 }
 
 #[derive(Serialize)]
@@ -54,7 +221,9 @@ pub async fn send_message(
     match body.validate() {
         Ok(_) => {
             let message = body.message.unwrap();
-            if message.is_inappropriate() {
+            let format = body.format.unwrap_or(MessageFormat::Plain);
+            let formatted = format_message(&message, format);
+            if formatted.visible_text.is_inappropriate() {
                 return (
                     StatusCode::BAD_REQUEST,
                     Json(SendMessageResponse {
@@ -66,20 +235,141 @@ pub async fn send_message(
                     }),
                 );
             }
+            if let Some(parent_id) = body.parent_id {
+                match find_thread_parent(&state, participant.room_id, parent_id).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        return (
+                            StatusCode::NOT_FOUND,
+                            Json(SendMessageResponse {
+                                success: false,
+                                http_code: 404,
+                                error: Some("Parent message not found".to_string()),
+                                message: None,
+                                validation_errors: None,
+                            }),
+                        )
+                    }
+                    Err(_) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(SendMessageResponse {
+                                success: false,
+                                http_code: 500,
+                                error: Some("Internal server error".to_string()),
+                                message: None,
+                                validation_errors: None,
+                            }),
+                        )
+                    }
+                };
+            }
+            let attachment_ids = body.attachment_ids.clone().unwrap_or_default();
+            if !attachment_ids.is_empty() {
+                let attachment_count = state
+                    .prisma_client
+                    .attachments()
+                    .count(vec![
+                        attachments::id::in_vec(attachment_ids.clone()),
+                        attachments::uploader_id::equals(participant.user_id),
+                        attachments::room_id::equals(participant.room_id),
+                        attachments::message_id::equals(None),
+                        attachments::state::not(AttachmentState::Failed),
+                    ])
+                    .exec()
+                    .await;
+                match attachment_count {
+                    Ok(count) if count as usize == attachment_ids.len() => {}
+                    Ok(_) => {
+                        return (
+                            StatusCode::BAD_REQUEST,
+                            Json(SendMessageResponse {
+                                success: false,
+                                http_code: 400,
+                                message: None,
+                                validation_errors: None,
+                                error: Some("Attachment not found or already sent".to_string()),
+                            }),
+                        )
+                    }
+                    Err(_) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(SendMessageResponse {
+                                success: false,
+                                http_code: 500,
+                                message: None,
+                                validation_errors: None,
+                                error: Some("Internal server error".to_string()),
+                            }),
+                        )
+                    }
+                };
+            }
+            let mut create_params = vec![
+                messages::format::set(format),
+                messages::rendered::set(formatted.rendered),
+            ];
+            if let Some(parent_id) = body.parent_id {
+                create_params.push(messages::parent::connect(
+                    messages::UniqueWhereParam::IdEquals(parent_id),
+                ));
+            }
+            let (user_id, room_id) = (participant.user_id, participant.room_id);
+            // The message only exists with every attachment it was sent with, a concurrent
+            // send that linked one first rolls this one back
             let message = state
                 .prisma_client
-                .messages()
-                .create(
-                    message.to_string(),
-                    user::UniqueWhereParam::IdEquals(participant.user_id.clone()),
-                    rooms::UniqueWhereParam::IdEquals(participant.room_id.clone()),
-                    vec![],
-                )
-                .exec()
+                ._transaction()
+                .run(|client| async move {
+                    let message = client
+                        .messages()
+                        .create(
+                            message.to_string(),
+                            user::UniqueWhereParam::IdEquals(user_id),
+                            rooms::UniqueWhereParam::IdEquals(room_id),
+                            create_params,
+                        )
+                        .exec()
+                        .await?;
+                    if !attachment_ids.is_empty() {
+                        let expected = attachment_ids.len() as i64;
+                        let linked = client
+                            .attachments()
+                            .update_many(
+                                vec![
+                                    attachments::id::in_vec(attachment_ids),
+                                    attachments::uploader_id::equals(user_id),
+                                    attachments::room_id::equals(room_id),
+                                    attachments::message_id::equals(None),
+                                    attachments::state::not(AttachmentState::Failed),
+                                ],
+                                vec![attachments::message_id::set(Some(message.id))],
+                            )
+                            .exec()
+                            .await?;
+                        if linked != expected {
+                            return Err(CreateMessageError::AttachmentsTaken);
+                        }
+                    }
+                    Ok(message)
+                })
                 .await;
             let message = match message {
                 Ok(message) => message,
-                Err(_) => {
+                Err(CreateMessageError::AttachmentsTaken) => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        Json(SendMessageResponse {
+                            success: false,
+                            http_code: 400,
+                            message: None,
+                            validation_errors: None,
+                            error: Some("Attachment not found or already sent".to_string()),
+                        }),
+                    )
+                }
+                Err(CreateMessageError::Query(_)) => {
                     return (
                         StatusCode::INTERNAL_SERVER_ERROR,
                         Json(SendMessageResponse {
@@ -92,21 +382,7 @@ pub async fn send_message(
                     );
                 }
             };
-            state
-                .redis_client
-                .publish(
-                    format!("chat:{}", participant.room_id),
-                    serde_json::to_string(&WebSocketMessage {
-                        record: crate::socket::interfaces::websocket_message::Records::Message,
-                        queue: format!("chat:{}", participant.room_id),
-                        data: serde_json::json!({
-                            "message_id": message.id,
-                        }),
-                    })
-                    .unwrap(),
-                )
-                .await
-                .ok();
+            publish_message(&state, &message).await;
             (
                 StatusCode::CREATED,
                 Json(SendMessageResponse {
@@ -149,3 +425,26 @@ pub async fn send_message(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_are_deduplicated_and_trimmed() {
+        assert_eq!(
+            parse_mentions("@alice and @bob. ping @alice"),
+            vec!["alice".to_string(), "bob".to_string()]
+        );
+    }
+
+    #[test]
+    fn email_addresses_are_not_mentions() {
+        assert!(parse_mentions("write to john@example.com").is_empty());
+    }
+
+    #[test]
+    fn short_names_are_ignored() {
+        assert!(parse_mentions("@al @").is_empty());
+    }
+}
@@ -0,0 +1,313 @@
+use axum::{
+    extract::{Multipart, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{attachments, rooms, user, users_rooms, AttachmentState},
+    shared::{arc_clients::State as AppState, attachment_limits::ATTACHMENT_LIMITS},
+};
+
+use super::process_attachment::{is_processed_image, quarantine_key, ImageJob};
+
+#[derive(Serialize)]
+pub struct AttachmentSummary {
+    pub id: String,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size: i32,
+    pub state: AttachmentState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
+    pub has_thumbnail: bool,
+}
+
+impl From<attachments::Data> for AttachmentSummary {
+    fn from(value: attachments::Data) -> Self {
+        Self {
+            id: value.id,
+            file_name: value.file_name,
+            mime_type: value.mime_type,
+            size: value.size,
+            state: value.state,
+            width: value.width,
+            height: value.height,
+            has_thumbnail: value.thumbnail_key.is_some(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct UploadAttachmentResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<AttachmentSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// The declared type is only trusted for plain text, anything else has to match its magic bytes
+pub fn detect_mime_type(bytes: &[u8], declared: Option<&str>) -> Option<String> {
+    match infer::get(bytes) {
+        Some(kind) => Some(kind.mime_type().to_string()),
+        None => {
+            let declared = declared.and_then(|declared| declared.split(';').next());
+            if declared.map(str::trim) == Some("text/plain") && std::str::from_utf8(bytes).is_ok() {
+                Some("text/plain".to_string())
+            } else {
+                None
+            }
+        }
+    }
+}
+
+// Drops any client side path and characters that would break a Content-Disposition header
+fn clean_file_name(file_name: Option<&str>) -> String {
+    let file_name = file_name
+        .and_then(|file_name| file_name.rsplit(['/', '\\']).next())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(255)
+        .collect::<String>();
+    let file_name = file_name.trim();
+    if file_name.is_empty() {
+        "file".to_string()
+    } else {
+        file_name.to_string()
+    }
+}
+
+pub async fn upload_attachment(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    mut multipart: Multipart,
+) -> (StatusCode, Json<UploadAttachmentResponse>) {
+    let mut field = loop {
+        match multipart.next_field().await {
+            Ok(Some(field)) if field.name() == Some("file") => break field,
+            Ok(Some(_)) => continue,
+            Ok(None) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(UploadAttachmentResponse {
+                        success: false,
+                        http_code: 400,
+                        attachment: None,
+                        error: Some("file is required".to_string()),
+                    }),
+                )
+            }
+            Err(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(UploadAttachmentResponse {
+                        success: false,
+                        http_code: 400,
+                        attachment: None,
+                        error: Some("Invalid multipart body".to_string()),
+                    }),
+                )
+            }
+        }
+    };
+    let file_name = clean_file_name(field.file_name());
+    let declared_type = field.content_type().map(|declared| declared.to_string());
+    // Read in chunks so oversized files are cut off without buffering them whole
+    let mut bytes: Vec<u8> = Vec::new();
+    loop {
+        match field.chunk().await {
+            Ok(Some(chunk)) => {
+                if bytes.len() + chunk.len() > ATTACHMENT_LIMITS.max_size {
+                    return (
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        Json(UploadAttachmentResponse {
+                            success: false,
+                            http_code: 413,
+                            attachment: None,
+                            error: Some("File exceeds the size limit".to_string()),
+                        }),
+                    );
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+            Ok(None) => break,
+            Err(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(UploadAttachmentResponse {
+                        success: false,
+                        http_code: 400,
+                        attachment: None,
+                        error: Some("Invalid multipart body".to_string()),
+                    }),
+                )
+            }
+        }
+    }
+    if bytes.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(UploadAttachmentResponse {
+                success: false,
+                http_code: 400,
+                attachment: None,
+                error: Some("File is empty".to_string()),
+            }),
+        );
+    }
+    let mime_type = match detect_mime_type(&bytes, declared_type.as_deref()) {
+        Some(mime_type) if ATTACHMENT_LIMITS.allows(&mime_type) => mime_type,
+        _ => {
+            return (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                Json(UploadAttachmentResponse {
+                    success: false,
+                    http_code: 415,
+                    attachment: None,
+                    error: Some("File type is not allowed".to_string()),
+                }),
+            )
+        }
+    };
+    let size = bytes.len() as i32;
+    let storage_key = format!("{}/{}", participant.room_id, uuid::Uuid::new_v4());
+    let processing = is_processed_image(&mime_type);
+    // Images are only stored under their key once the worker has stripped their metadata,
+    // until then the raw upload sits in quarantine
+    let pending_image = if processing {
+        let stored = state
+            .storage
+            .put(&quarantine_key(&storage_key), &mime_type, bytes.clone())
+            .await;
+        if stored.is_err() {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(UploadAttachmentResponse {
+                    success: false,
+                    http_code: 500,
+                    attachment: None,
+                    error: Some("Failed to store file".to_string()),
+                }),
+            );
+        }
+        Some(bytes)
+    } else {
+        let stored = state.storage.put(&storage_key, &mime_type, bytes).await;
+        if stored.is_err() {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(UploadAttachmentResponse {
+                    success: false,
+                    http_code: 500,
+                    attachment: None,
+                    error: Some("Failed to store file".to_string()),
+                }),
+            );
+        }
+        None
+    };
+    let attachment = state
+        .prisma_client
+        .attachments()
+        .create(
+            file_name,
+            mime_type,
+            size,
+            storage_key.clone(),
+            user::UniqueWhereParam::IdEquals(participant.user_id),
+            rooms::UniqueWhereParam::IdEquals(participant.room_id),
+            vec![attachments::state::set(if processing {
+                AttachmentState::Processing
+            } else {
+                AttachmentState::Ready
+            })],
+        )
+        .exec()
+        .await;
+    let attachment = match attachment {
+        Ok(attachment) => attachment,
+        Err(_) => {
+            // Don't leave an object behind that no row points at
+            if processing {
+                state
+                    .storage
+                    .delete(&quarantine_key(&storage_key))
+                    .await
+                    .ok();
+            } else {
+                state.storage.delete(&storage_key).await.ok();
+            }
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(UploadAttachmentResponse {
+                    success: false,
+                    http_code: 500,
+                    attachment: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            );
+        }
+    };
+    if let Some(bytes) = pending_image {
+        let queued = state
+            .image_jobs
+            .send(ImageJob {
+                attachment_id: attachment.id.clone(),
+                storage_key,
+                mime_type: attachment.mime_type.clone(),
+                bytes,
+            })
+            .await;
+        // The row stays PROCESSING, the next startup recovers it from quarantine
+        if queued.is_err() {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(UploadAttachmentResponse {
+                    success: false,
+                    http_code: 500,
+                    attachment: None,
+                    error: Some("Failed to queue image processing".to_string()),
+                }),
+            );
+        }
+    }
+    (
+        StatusCode::CREATED,
+        Json(UploadAttachmentResponse {
+            success: true,
+            http_code: 201,
+            attachment: Some(AttachmentSummary::from(attachment)),
+            error: None,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_wins_over_the_declared_type() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(
+            detect_mime_type(png, Some("text/plain")),
+            Some("image/png".to_string())
+        );
+    }
+
+    #[test]
+    fn text_needs_declaring_and_valid_utf8() {
+        assert_eq!(
+            detect_mime_type(b"hello", Some("text/plain; charset=utf-8")),
+            Some("text/plain".to_string())
+        );
+        assert_eq!(detect_mime_type(b"hello", None), None);
+        assert_eq!(detect_mime_type(b"caf\xc3\x28", Some("text/plain")), None);
+    }
+}
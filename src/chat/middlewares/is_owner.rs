@@ -6,10 +6,11 @@ use axum::{
 };
 use serde::Serialize;
 
-use crate::prisma_client::client::{user, users_rooms};
+use crate::prisma_client::client::{user, users_rooms, RoomKind};
 
 enum ParticipantError {
     NotOwner,
+    NoOwner,
 }
 
 #[derive(Serialize)]
@@ -23,6 +24,7 @@ impl IntoResponse for ParticipantError {
     fn into_response(self) -> axum::response::Response {
         let error_message: String = match self {
             ParticipantError::NotOwner => "Not the owner".to_string(),
+            ParticipantError::NoOwner => "This conversation has no owner".to_string(),
         };
         match self {
             ParticipantError::NotOwner => (
@@ -33,6 +35,14 @@ impl IntoResponse for ParticipantError {
                     error: error_message,
                 }),
             ),
+            ParticipantError::NoOwner => (
+                StatusCode::FORBIDDEN,
+                Json(ParticipantErrorResponse {
+                    success: false,
+                    http_code: 403,
+                    error: error_message,
+                }),
+            ),
         }
         .into_response()
     }
@@ -44,7 +54,12 @@ pub async fn is_owner<B>(
     request: Request<B>,
     next: Next<B>,
 ) -> Response {
-    let is_owner = participant.room.unwrap().user_id == user.id;
+    let room = participant.room.unwrap();
+    // Direct and group conversations have no staff to check against
+    if room.kind != RoomKind::Group {
+        return ParticipantError::NoOwner.into_response();
+    }
+    let is_owner = room.user_id == Some(user.id);
     if !is_owner {
         return ParticipantError::NotOwner.into_response();
     }
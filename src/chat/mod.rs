@@ -1,6 +1,8 @@
 pub mod chat_router;
+pub mod direct;
 pub mod interfaces;
 pub mod invites;
 pub mod messages;
 pub mod middlewares;
+pub mod requests;
 pub mod rooms;
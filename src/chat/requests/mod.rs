@@ -0,0 +1,4 @@
+pub mod interfaces;
+pub mod resolve_request;
+pub mod retrieve_requests;
+pub mod submit_request;
@@ -0,0 +1,398 @@
+use axum::{
+    extract::{Json as ExtractJson, Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use rustis::commands::PubSubCommands;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    chat::rooms::moderation::{ban_user::is_banned, kick_user::kick_cooldown},
+    error::validation_error::ValidationError,
+    prisma_client::client::{join_requests, rooms, user, users_rooms, JoinRequestState},
+    rejection::{json::CustomJsonDataRejection, path::CustomPathDataRejection},
+    shared::{arc_clients::State as AppState, room_limits::RoomLimits},
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+use super::interfaces::request_id_param::RequestIdParam;
+
+#[derive(Deserialize, Serialize)]
+pub enum JoinRequestReaction {
+    Approve,
+    Deny,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ResolveRequestBody {
+    #[validate(required(message = "Invalid reaction"))]
+    pub reaction: Option<JoinRequestReaction>,
+}
+
+#[derive(Serialize)]
+pub struct ResolveRequestResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Adds the requester to the room, false when they already joined some other way
+async fn admit_requester(
+    state: &AppState,
+    participant: &users_rooms::Data,
+    request: &join_requests::Data,
+) -> Result<bool, (StatusCode, Json<ResolveRequestResponse>)> {
+    let room = match participant.room.as_ref() {
+        Some(room) => room,
+        None => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ResolveRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            ))
+        }
+    };
+    if room.archived {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ResolveRequestResponse {
+                success: false,
+                http_code: 403,
+                validation_errors: None,
+                error: Some("Chat is archived".to_string()),
+            }),
+        ));
+    }
+    match is_banned(state.prisma_client.clone(), request.user_id, room.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ResolveRequestResponse {
+                    success: false,
+                    http_code: 400,
+                    validation_errors: None,
+                    error: Some("User is banned from this room".to_string()),
+                }),
+            ))
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ResolveRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            ))
+        }
+    };
+    match kick_cooldown(state.redis_client.clone(), request.user_id, room.id).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ResolveRequestResponse {
+                    success: false,
+                    http_code: 403,
+                    validation_errors: None,
+                    error: Some("User was recently kicked from this room".to_string()),
+                }),
+            ))
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ResolveRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            ))
+        }
+    };
+    let existing = state
+        .prisma_client
+        .users_rooms()
+        .find_first(vec![
+            users_rooms::user_id::equals(request.user_id),
+            users_rooms::room_id::equals(room.id),
+        ])
+        .exec()
+        .await;
+    match existing {
+        Ok(None) => {}
+        Ok(Some(_)) => return Ok(false),
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ResolveRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            ))
+        }
+    };
+    let participant_count = state
+        .prisma_client
+        .users_rooms()
+        .count(vec![users_rooms::room_id::equals(room.id)])
+        .exec()
+        .await;
+    match participant_count {
+        Ok(participant_count) if RoomLimits::is_full(room.capacity, participant_count) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ResolveRequestResponse {
+                    success: false,
+                    http_code: 400,
+                    validation_errors: None,
+                    error: Some("Room is full".to_string()),
+                }),
+            ))
+        }
+        Ok(_) => {}
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ResolveRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            ))
+        }
+    };
+    let participant_insertion = state
+        .prisma_client
+        .users_rooms()
+        .create(
+            user::UniqueWhereParam::IdEquals(request.user_id),
+            rooms::UniqueWhereParam::IdEquals(room.id),
+            vec![],
+        )
+        .exec()
+        .await;
+    match participant_insertion {
+        Ok(_) => Ok(true),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ResolveRequestResponse {
+                success: false,
+                http_code: 500,
+                validation_errors: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        )),
+    }
+}
+
+pub async fn resolve_request(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(RequestIdParam { request_id }), _): WithRejection<
+        Path<RequestIdParam>,
+        CustomPathDataRejection,
+    >,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<ResolveRequestBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<ResolveRequestResponse>) {
+    match body.validate() {
+        Ok(_) => {
+            let reaction = body.reaction.unwrap();
+            let request = state
+                .prisma_client
+                .join_requests()
+                .find_first(vec![
+                    join_requests::id::equals(request_id),
+                    join_requests::room_id::equals(participant.room_id),
+                    join_requests::state::equals(JoinRequestState::Pending),
+                ])
+                .exec()
+                .await;
+            let request = match request {
+                Ok(Some(request)) => request,
+                Ok(None) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(ResolveRequestResponse {
+                            success: false,
+                            http_code: 404,
+                            validation_errors: None,
+                            error: Some("Join request not found".to_string()),
+                        }),
+                    )
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ResolveRequestResponse {
+                            success: false,
+                            http_code: 500,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let resolved_state = match reaction {
+                JoinRequestReaction::Approve => JoinRequestState::Approved,
+                JoinRequestReaction::Deny => JoinRequestState::Denied,
+            };
+            // Claimed before anything else so two staff members can't both resolve it
+            let claimed = state
+                .prisma_client
+                .join_requests()
+                .update_many(
+                    vec![
+                        join_requests::id::equals(request.id.clone()),
+                        join_requests::state::equals(JoinRequestState::Pending),
+                    ],
+                    vec![
+                        join_requests::state::set(resolved_state),
+                        join_requests::resolver_id::set(Some(participant.user_id)),
+                    ],
+                )
+                .exec()
+                .await;
+            match claimed {
+                Ok(1) => {}
+                Ok(_) => {
+                    return (
+                        StatusCode::CONFLICT,
+                        Json(ResolveRequestResponse {
+                            success: false,
+                            http_code: 409,
+                            validation_errors: None,
+                            error: Some("Join request was already resolved".to_string()),
+                        }),
+                    )
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ResolveRequestResponse {
+                            success: false,
+                            http_code: 500,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let mut joined = false;
+            if resolved_state == JoinRequestState::Approved {
+                match admit_requester(&state, &participant, &request).await {
+                    Ok(admitted) => joined = admitted,
+                    Err(response) => {
+                        // Back in the queue, the refusal may not last (cooldown, capacity)
+                        state
+                            .prisma_client
+                            .join_requests()
+                            .update_many(
+                                vec![
+                                    join_requests::id::equals(request.id.clone()),
+                                    join_requests::state::equals(JoinRequestState::Approved),
+                                ],
+                                vec![
+                                    join_requests::state::set(JoinRequestState::Pending),
+                                    join_requests::resolver_id::set(None),
+                                ],
+                            )
+                            .exec()
+                            .await
+                            .ok();
+                        return response;
+                    }
+                };
+            }
+            state
+                .redis_client
+                .publish(
+                    format!("priv_user:{}", request.user_id),
+                    serde_json::to_string(&WebSocketMessage {
+                        record: Records::Message,
+                        queue: format!("chat-{}", participant.room_id),
+                        data: serde_json::json!({
+                            "request_id": request.id,
+                            "state": resolved_state,
+                            "type": "join_request",
+                        }),
+                    })
+                    .unwrap(),
+                )
+                .await
+                .ok();
+            if joined {
+                state
+                    .redis_client
+                    .publish(
+                        format!("chat:{}", participant.room_id),
+                        serde_json::to_string(&WebSocketMessage {
+                            record: Records::ParticipantJoined,
+                            queue: format!("chat:{}", participant.room_id),
+                            data: serde_json::json!({
+                                "user_id": request.user_id,
+                            }),
+                        })
+                        .unwrap(),
+                    )
+                    .await
+                    .ok();
+            }
+            (
+                StatusCode::OK,
+                Json(ResolveRequestResponse {
+                    success: true,
+                    http_code: 200,
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ResolveRequestResponse {
+                    success: false,
+                    http_code: 422,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
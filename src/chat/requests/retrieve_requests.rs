@@ -0,0 +1,137 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::Serialize;
+use validator::Validate;
+
+use crate::{
+    chat::interfaces::pagination_query::PaginationQuery,
+    error::validation_error::ValidationError,
+    prisma_client::client::{join_requests, users_rooms, JoinRequestState},
+    rejection::query::CustomQueryDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Serialize)]
+pub struct Requester {
+    pub id: i32,
+    pub username: String,
+}
+
+#[derive(Serialize)]
+pub struct JoinRequest {
+    pub id: String,
+    pub requester: Requester,
+    pub note: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<join_requests::Data> for JoinRequest {
+    fn from(value: join_requests::Data) -> Self {
+        let user = value.user.unwrap();
+        Self {
+            id: value.id,
+            requester: Requester {
+                id: user.id,
+                username: user.username,
+            },
+            note: value.note,
+            created_at: value.created_at.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RetrieveRequestsResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests: Option<Vec<JoinRequest>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn retrieve_requests(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Query(pagination), _): WithRejection<
+        Query<PaginationQuery>,
+        CustomQueryDataRejection,
+    >,
+) -> (StatusCode, Json<RetrieveRequestsResponse>) {
+    match pagination.validate() {
+        Ok(_) => {
+            let requests = state
+                .prisma_client
+                .join_requests()
+                .find_many(vec![
+                    join_requests::room_id::equals(participant.room_id),
+                    join_requests::state::equals(JoinRequestState::Pending),
+                ])
+                .with(join_requests::user::fetch())
+                .order_by(join_requests::created_at::order(
+                    prisma_client_rust::Direction::Asc,
+                ))
+                .skip(pagination.skip())
+                .take(pagination.take())
+                .exec()
+                .await;
+            match requests {
+                Ok(requests) => (
+                    StatusCode::OK,
+                    Json(RetrieveRequestsResponse {
+                        success: true,
+                        http_code: 200,
+                        requests: Some(requests.into_iter().map(JoinRequest::from).collect()),
+                        validation_errors: None,
+                        error: None,
+                    }),
+                ),
+                Err(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(RetrieveRequestsResponse {
+                        success: false,
+                        http_code: 500,
+                        requests: None,
+                        validation_errors: None,
+                        error: Some("Internal server error".to_string()),
+                    }),
+                ),
+            }
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(RetrieveRequestsResponse {
+                    success: false,
+                    http_code: 422,
+                    requests: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use axum::{
+    extract::{Json as ExtractJson, Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use rustis::commands::PubSubCommands;
+use rustrict::CensorStr;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    chat::rooms::{
+        interfaces::params_chat::RetrieveChatParams,
+        moderation::{ban_user::is_banned, kick_user::kick_cooldown},
+    },
+    error::validation_error::ValidationError,
+    prisma_client::client::{
+        join_requests, rooms, user, users_rooms, JoinPolicy, JoinRequestState, RoomKind,
+    },
+    rejection::{json::CustomJsonDataRejection, path::CustomPathDataRejection},
+    shared::arc_clients::State as AppState,
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+#[derive(Deserialize, Validate)]
+pub struct SubmitRequestBody {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "note must be between 1 and 255 characters"
+    ))]
+    pub note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SubmitRequestResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn submit_request(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Path(chat_params), _): WithRejection<
+        Path<RetrieveChatParams>,
+        CustomPathDataRejection,
+    >,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<SubmitRequestBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<SubmitRequestResponse>) {
+    if chat_params.id > i32::MAX as u64 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SubmitRequestResponse {
+                success: false,
+                http_code: 400,
+                request_id: None,
+                validation_errors: None,
+                error: Some("Chat id cannot exceed 32 bits signed".to_string()),
+            }),
+        );
+    }
+    if let Err(validation_errors) = body.validate() {
+        let validation_errors =
+            validation_errors
+                .field_errors()
+                .into_iter()
+                .map(|(field, errors)| ValidationError {
+                    field: field.to_string(),
+                    // Message is a cow
+                    messages: errors
+                        .iter()
+                        .map(|e| {
+                            e.message
+                                .as_ref()
+                                .map(|m| m.to_string())
+                                .unwrap_or_else(|| "Unknown error".to_string())
+                        })
+                        .collect(),
+                });
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(SubmitRequestResponse {
+                success: false,
+                http_code: 422,
+                request_id: None,
+                validation_errors: Some(validation_errors.collect()),
+                error: None,
+            }),
+        );
+    }
+    if let Some(note) = &body.note {
+        if note.is_inappropriate() {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 422,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Note is inappropriate".to_string()),
+                }),
+            );
+        }
+    }
+    let chat_id = chat_params.id as i32;
+    let chat = state
+        .prisma_client
+        .rooms()
+        .find_unique(rooms::UniqueWhereParam::IdEquals(chat_id))
+        .exec()
+        .await;
+    let chat = match chat {
+        Ok(Some(chat)) => chat,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 404,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Chat not found".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    if chat.archived {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(SubmitRequestResponse {
+                success: false,
+                http_code: 403,
+                request_id: None,
+                validation_errors: None,
+                error: Some("Chat is archived".to_string()),
+            }),
+        );
+    }
+    if chat.join_policy != JoinPolicy::Request || chat.kind != RoomKind::Group {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SubmitRequestResponse {
+                success: false,
+                http_code: 400,
+                request_id: None,
+                validation_errors: None,
+                error: Some("This room does not take join requests".to_string()),
+            }),
+        );
+    }
+    match is_banned(state.prisma_client.clone(), user.id, chat.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 403,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("You are banned from this room.".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    match kick_cooldown(state.redis_client.clone(), user.id, chat.id).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 403,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("You were recently kicked from this room".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let is_participant = state
+        .prisma_client
+        .users_rooms()
+        .find_first(vec![
+            users_rooms::user_id::equals(user.id),
+            users_rooms::room_id::equals(chat.id),
+        ])
+        .exec()
+        .await;
+    match is_participant {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 409,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Already participant".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let pending_request = state
+        .prisma_client
+        .join_requests()
+        .find_first(vec![
+            join_requests::user_id::equals(user.id),
+            join_requests::room_id::equals(chat.id),
+            join_requests::state::equals(JoinRequestState::Pending),
+        ])
+        .exec()
+        .await;
+    match pending_request {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 409,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Join request already pending".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    let request = state
+        .prisma_client
+        .join_requests()
+        .create(
+            user::UniqueWhereParam::IdEquals(user.id),
+            rooms::UniqueWhereParam::IdEquals(chat.id),
+            vec![join_requests::note::set(body.note)],
+        )
+        .exec()
+        .await;
+    let request = match request {
+        Ok(request) => request,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SubmitRequestResponse {
+                    success: false,
+                    http_code: 500,
+                    request_id: None,
+                    validation_errors: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    // Notify the room staff through their private channel
+    if let Some(owner_id) = chat.user_id {
+        state
+            .redis_client
+            .publish(
+                format!("priv_user:{}", owner_id),
+                serde_json::to_string(&WebSocketMessage {
+                    record: Records::Message,
+                    queue: format!("chat-{}", chat.id),
+                    data: serde_json::json!({
+                        "request_id": request.id,
+                        "user_id": user.id,
+                        "type": "join_request",
+                    }),
+                })
+                .unwrap(),
+            )
+            .await
+            .ok();
+    }
+    (
+        StatusCode::CREATED,
+        Json(SubmitRequestResponse {
+            success: true,
+            http_code: 201,
+            request_id: Some(request.id),
+            validation_errors: None,
+            error: None,
+        }),
+    )
+}
@@ -1,5 +1,6 @@
 use crate::prisma_client::client::user;
 use crate::shared::arc_clients::State as AppState;
+use crate::shared::room_limits::{ROOM_LIMITS, UNLIMITED_CAPACITY};
 use crate::{
     error::validation_error::ValidationError, prisma_client::client::rooms,
     rejection::json::CustomJsonDataRejection,
@@ -26,18 +27,15 @@ pub struct CreateChatBody {
         )
     )]
     pub name: Option<String>,
-    // Capacity u8
-    #[validate(
-        required(message = "The field `capacity` is required"),
-        range(min = 1, max = 10, message = "Capacity must be between 1 and 10")
-    )]
-    pub capacity: Option<u8>,
+    // The upper bound is checked against the server-wide room limits
+    #[validate(required(message = "The field `capacity` is required"))]
+    pub capacity: Option<u32>,
 }
 
 #[derive(Serialize)]
 pub struct CreateChat {
     pub name: String,
-    pub capacity: u8,
+    pub capacity: u32,
 }
 
 impl From<rooms::Data> for CreateChat {
@@ -70,6 +68,35 @@ pub async fn create_chat(
     match body.validate() {
         Ok(_) => {
             let (name, capacity) = (body.name.unwrap(), body.capacity.unwrap());
+            let capacity_allowed = match capacity {
+                UNLIMITED_CAPACITY => ROOM_LIMITS.allow_unlimited,
+                capacity => capacity <= ROOM_LIMITS.max_capacity,
+            };
+            if !capacity_allowed {
+                let message = if ROOM_LIMITS.allow_unlimited {
+                    format!(
+                        "Capacity must be between 1 and {} or 0 for unlimited",
+                        ROOM_LIMITS.max_capacity
+                    )
+                } else {
+                    format!(
+                        "Capacity must be between 1 and {}",
+                        ROOM_LIMITS.max_capacity
+                    )
+                };
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(CreateChatResponse {
+                        success: false,
+                        http_code: 422,
+                        chat: None,
+                        errors: Some(vec![ValidationError {
+                            field: "capacity".to_string(),
+                            messages: vec![message],
+                        }]),
+                    }),
+                );
+            }
             if name.is_inappropriate() {
                 return (
                     StatusCode::UNPROCESSABLE_ENTITY,
@@ -90,8 +117,9 @@ pub async fn create_chat(
                 .create(
                     name.clone(),
                     capacity.try_into().unwrap(),
-                    user::UniqueWhereParam::IdEquals(user.id),
-                    vec![],
+                    vec![rooms::user::connect(user::UniqueWhereParam::IdEquals(
+                        user.id,
+                    ))],
                 )
                 .exec()
                 .await;
@@ -8,13 +8,17 @@ use rustis::commands::PubSubCommands;
 use serde::Serialize;
 
 use crate::{
-    prisma_client::client::{banned_users_room, rooms, user, users_rooms},
+    prisma_client::client::{rooms, user, users_rooms, JoinPolicy, RoomKind},
     rejection::path::CustomPathDataRejection,
-    shared::arc_clients::State as AppState,
+    shared::{arc_clients::State as AppState, room_limits::RoomLimits},
     socket::interfaces::websocket_message::WebSocketMessage,
 };
 
-use super::{interfaces::params_chat::RetrieveChatParams, retrieve_chat::Chat};
+use super::{
+    interfaces::params_chat::RetrieveChatParams,
+    moderation::{ban_user::is_banned, kick_user::kick_cooldown},
+    retrieve_chat::Chat,
+};
 
 #[derive(Serialize)]
 pub struct JoinChatResponse {
@@ -51,7 +55,6 @@ pub async fn join_chat(
         .find_unique(rooms::UniqueWhereParam::IdEquals(
             chat_param.id.try_into().unwrap(),
         ))
-        .with(rooms::users_rooms::fetch(vec![]))
         .exec()
         .await;
     let chat = match chat {
@@ -82,17 +85,56 @@ pub async fn join_chat(
             );
         }
     };
-    let is_banned = state
-        .prisma_client
-        .banned_users_room()
-        .find_first(vec![
-            banned_users_room::user_id::equals(user.id),
-            banned_users_room::room_id::equals(chat.id),
-        ])
-        .exec()
-        .await;
+    if chat.kind != RoomKind::Group {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(JoinChatResponse {
+                success: false,
+                http_code: 404,
+                chat: None,
+                error: Some("Chat not found".to_string()),
+            }),
+        );
+    }
+    if chat.archived {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(JoinChatResponse {
+                success: false,
+                http_code: 403,
+                chat: None,
+                error: Some("Chat is archived".to_string()),
+            }),
+        );
+    }
+    match chat.join_policy {
+        JoinPolicy::Open => {}
+        JoinPolicy::Request => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(JoinChatResponse {
+                    success: false,
+                    http_code: 403,
+                    chat: None,
+                    error: Some("This room requires a join request".to_string()),
+                }),
+            );
+        }
+        JoinPolicy::Invite => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(JoinChatResponse {
+                    success: false,
+                    http_code: 403,
+                    chat: None,
+                    error: Some("This room is invite only".to_string()),
+                }),
+            );
+        }
+    };
+    let is_banned = is_banned(state.prisma_client.clone(), user.id, chat.id).await;
     let is_banned = match is_banned {
-        Ok(banned) => banned.is_some(),
+        Ok(banned) => banned,
         Err(_) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -116,10 +158,58 @@ pub async fn join_chat(
             }),
         );
     }
-    let participants = chat.users_rooms.unwrap();
-    let is_already_participant: bool = participants
-        .iter()
-        .any(|participant| participant.user_id == user.id);
+    let cooldown = kick_cooldown(state.redis_client.clone(), user.id, chat.id).await;
+    let cooldown = match cooldown {
+        Ok(cooldown) => cooldown,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(JoinChatResponse {
+                    success: false,
+                    http_code: 500,
+                    chat: None,
+                    error: Some("Internal Server Error".to_string()),
+                }),
+            );
+        }
+    };
+    if let Some(cooldown) = cooldown {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(JoinChatResponse {
+                success: false,
+                http_code: 403,
+                chat: None,
+                error: Some(format!(
+                    "You were kicked from this room, try again in {} seconds.",
+                    cooldown
+                )),
+            }),
+        );
+    }
+    let is_already_participant = state
+        .prisma_client
+        .users_rooms()
+        .find_first(vec![
+            users_rooms::user_id::equals(user.id),
+            users_rooms::room_id::equals(chat.id),
+        ])
+        .exec()
+        .await;
+    let is_already_participant = match is_already_participant {
+        Ok(participant) => participant.is_some(),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(JoinChatResponse {
+                    success: false,
+                    http_code: 500,
+                    chat: None,
+                    error: Some("Internal Server Error".to_string()),
+                }),
+            );
+        }
+    };
     if is_already_participant {
         return (
             StatusCode::CONFLICT,
@@ -131,7 +221,27 @@ pub async fn join_chat(
             }),
         );
     }
-    if participants.len() >= chat.capacity as usize {
+    let participant_count = state
+        .prisma_client
+        .users_rooms()
+        .count(vec![users_rooms::room_id::equals(chat.id)])
+        .exec()
+        .await;
+    let participant_count = match participant_count {
+        Ok(participant_count) => participant_count,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(JoinChatResponse {
+                    success: false,
+                    http_code: 500,
+                    chat: None,
+                    error: Some("Internal Server Error".to_string()),
+                }),
+            );
+        }
+    };
+    if RoomLimits::is_full(chat.capacity, participant_count) {
         return (
             StatusCode::BAD_REQUEST,
             Json(JoinChatResponse {
@@ -176,7 +286,12 @@ pub async fn join_chat(
         .find_unique(rooms::UniqueWhereParam::IdEquals(
             chat_param.id.try_into().unwrap(),
         ))
-        .with(rooms::users_rooms::fetch(vec![]).with(users_rooms::user::fetch()))
+        .with(
+            rooms::users_rooms::fetch(vec![])
+                .order_by(users_rooms::id::order(prisma_client_rust::Direction::Asc))
+                .take(25)
+                .with(users_rooms::user::fetch()),
+        )
         .exec()
         .await;
 
@@ -227,7 +342,7 @@ pub async fn join_chat(
         Json(JoinChatResponse {
             success: true,
             http_code: 201,
-            chat: Some(chat.into()),
+            chat: Some(Chat::new(chat, participant_count + 1)),
             error: None,
         }),
     )
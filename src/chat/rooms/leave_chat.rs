@@ -4,11 +4,15 @@ use axum::{
     Extension, Json,
 };
 use axum_extra::extract::WithRejection;
+use prisma_client_rust::QueryError;
 use rustis::commands::PubSubCommands;
 use serde::Serialize;
 
 use crate::{
-    prisma_client::client::{user, users_rooms},
+    chat::messages::{process_attachment::quarantine_key, read_messages::clear_unread},
+    prisma_client::client::{
+        attachments, messages, rooms, scheduled_messages, user, users_rooms, RoomKind,
+    },
     rejection::path::CustomPathDataRejection,
     shared::arc_clients::State as AppState,
 };
@@ -22,6 +26,69 @@ pub struct LeaveChatErrorResponse {
     pub error: String,
 }
 
+// Group conversations only live as long as they have members
+async fn dissolve_if_empty(state: &AppState, room_id: i32) -> Result<bool, QueryError> {
+    // Counted again inside the transaction, a join landing in between keeps the room alive
+    // instead of leaving a member in a half deleted room
+    let stored_attachments = state
+        .prisma_client
+        ._transaction()
+        .run(|client| async move {
+            let participant_count = client
+                .users_rooms()
+                .count(vec![users_rooms::room_id::equals(room_id)])
+                .exec()
+                .await?;
+            if participant_count > 0 {
+                return Ok(None);
+            }
+            // Sent and unsent uploads alike, the rows are gone once the room is
+            let stored_attachments = client
+                .attachments()
+                .find_many(vec![attachments::room_id::equals(room_id)])
+                .exec()
+                .await?;
+            client
+                .attachments()
+                .delete_many(vec![attachments::room_id::equals(room_id)])
+                .exec()
+                .await?;
+            client
+                .scheduled_messages()
+                .delete_many(vec![scheduled_messages::room_id::equals(room_id)])
+                .exec()
+                .await?;
+            client
+                .messages()
+                .delete_many(vec![messages::room_id::equals(room_id)])
+                .exec()
+                .await?;
+            client
+                .rooms()
+                .delete(rooms::UniqueWhereParam::IdEquals(room_id))
+                .exec()
+                .await?;
+            Ok::<_, QueryError>(Some(stored_attachments))
+        })
+        .await?;
+    let stored_attachments = match stored_attachments {
+        Some(stored_attachments) => stored_attachments,
+        None => return Ok(false),
+    };
+    for attachment in stored_attachments {
+        state.storage.delete(&attachment.storage_key).await.ok();
+        state
+            .storage
+            .delete(&quarantine_key(&attachment.storage_key))
+            .await
+            .ok();
+        if let Some(thumbnail_key) = attachment.thumbnail_key {
+            state.storage.delete(&thumbnail_key).await.ok();
+        }
+    }
+    Ok(true)
+}
+
 pub async fn leave_chat(
     State(state): State<AppState>,
     Extension(user): Extension<user::Data>,
@@ -77,7 +144,19 @@ pub async fn leave_chat(
             ));
         }
     };
-    let is_owner = is_participant.room.unwrap().user_id == user.id;
+    let room = is_participant.room.clone().unwrap();
+    if room.kind == RoomKind::Direct {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(LeaveChatErrorResponse {
+                success: false,
+                http_code: 400,
+                error: "Direct conversations cannot be left".to_string(),
+            }),
+        ));
+    }
+    // Group conversations have no owner, anyone may leave them
+    let is_owner = room.kind == RoomKind::Group && room.user_id == Some(user.id);
     if is_owner {
         return Err((
             StatusCode::FORBIDDEN,
@@ -108,6 +187,22 @@ pub async fn leave_chat(
             ));
         }
     }
+    clear_unread(state.redis_client.clone(), user.id, room.id)
+        .await
+        .ok();
+    if room.kind == RoomKind::GroupDirect {
+        let dissolved = dissolve_if_empty(&state, room.id).await;
+        if dissolved.is_err() {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(LeaveChatErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ));
+        }
+    }
     state
         .redis_client
         .publish(
@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{rooms, user, users_rooms, RoomKind},
+    rejection::query::CustomQueryDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct ListChatsQuery {
+    // Archived rooms are left out unless explicitly requested
+    pub archived: Option<bool>,
+    #[validate(range(min = 1, message = "page must be greater than 0"))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 50, message = "limit must be between 1 and 50"))]
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ChatSummary {
+    pub id: i32,
+    pub name: String,
+    pub capacity: u32,
+    pub archived: bool,
+    pub is_owner: bool,
+}
+
+#[derive(Serialize)]
+pub struct ListChatsResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chats: Option<Vec<ChatSummary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn list_chats(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Query(query), _): WithRejection<Query<ListChatsQuery>, CustomQueryDataRejection>,
+) -> (StatusCode, Json<ListChatsResponse>) {
+    match query.validate() {
+        Ok(_) => {
+            let limit = query.limit.unwrap_or(25);
+            let participations = state
+                .prisma_client
+                .users_rooms()
+                .find_many(vec![
+                    users_rooms::user_id::equals(user.id),
+                    users_rooms::room::is(vec![
+                        rooms::archived::equals(query.archived.unwrap_or(false)),
+                        rooms::kind::equals(RoomKind::Group),
+                    ]),
+                ])
+                .with(users_rooms::room::fetch())
+                .order_by(users_rooms::id::order(prisma_client_rust::Direction::Desc))
+                .skip((query.page.unwrap_or(1) - 1).saturating_mul(limit))
+                .take(limit)
+                .exec()
+                .await;
+            match participations {
+                Ok(participations) => {
+                    let chats = participations
+                        .into_iter()
+                        .map(|participation| {
+                            let room = participation.room.unwrap();
+                            ChatSummary {
+                                id: room.id,
+                                name: room.name,
+                                capacity: room.capacity.try_into().unwrap(),
+                                archived: room.archived,
+                                is_owner: room.user_id == Some(user.id),
+                            }
+                        })
+                        .collect();
+                    (
+                        StatusCode::OK,
+                        Json(ListChatsResponse {
+                            success: true,
+                            http_code: 200,
+                            chats: Some(chats),
+                            validation_errors: None,
+                            error: None,
+                        }),
+                    )
+                }
+                Err(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ListChatsResponse {
+                        success: false,
+                        http_code: 500,
+                        chats: None,
+                        validation_errors: None,
+                        error: Some("Internal server error".to_string()),
+                    }),
+                ),
+            }
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ListChatsResponse {
+                    success: false,
+                    http_code: 422,
+                    chats: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
@@ -3,5 +3,7 @@ pub mod delete_chat;
 pub mod interfaces;
 pub mod join_chat;
 pub mod leave_chat;
+pub mod list_chats;
 pub mod moderation;
 pub mod retrieve_chat;
+pub mod retrieve_unread;
@@ -0,0 +1,98 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use rustis::commands::PubSubCommands;
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{rooms, users_rooms},
+    shared::arc_clients::State as AppState,
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+#[derive(Serialize)]
+pub struct ArchiveChatErrorResponse {
+    pub success: bool,
+    pub http_code: u16,
+    pub error: String,
+}
+
+async fn set_archived(
+    state: AppState,
+    room: rooms::Data,
+    archived: bool,
+) -> Result<StatusCode, (StatusCode, Json<ArchiveChatErrorResponse>)> {
+    if room.archived == archived {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ArchiveChatErrorResponse {
+                success: false,
+                http_code: 409,
+                error: if archived {
+                    "Chat is already archived".to_string()
+                } else {
+                    "Chat is not archived".to_string()
+                },
+            }),
+        ));
+    }
+    let archived_at = if archived {
+        Some(chrono::Utc::now().into())
+    } else {
+        None
+    };
+    let update = state
+        .prisma_client
+        .rooms()
+        .update(
+            rooms::UniqueWhereParam::IdEquals(room.id),
+            vec![
+                rooms::archived::set(archived),
+                rooms::archived_at::set(archived_at),
+            ],
+        )
+        .exec()
+        .await;
+    match update {
+        Ok(_) => {}
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ArchiveChatErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ));
+        }
+    };
+    state
+        .redis_client
+        .publish(
+            format!("chat:{}", room.id),
+            serde_json::to_string(&WebSocketMessage {
+                record: Records::Message,
+                queue: format!("chat:{}", room.id),
+                data: serde_json::json!({
+                    "chat_id": room.id,
+                    "action": if archived { "archive" } else { "unarchive" },
+                }),
+            })
+            .unwrap(),
+        )
+        .await
+        .ok();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn archive_chat(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+) -> Result<StatusCode, (StatusCode, Json<ArchiveChatErrorResponse>)> {
+    set_archived(state, *participant.room.unwrap(), true).await
+}
+
+pub async fn unarchive_chat(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+) -> Result<StatusCode, (StatusCode, Json<ArchiveChatErrorResponse>)> {
+    set_archived(state, *participant.room.unwrap(), false).await
+}
@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{
+        moderation_logs, rooms, user, users_rooms, ModerationAction, PrismaClient,
+    },
+    rejection::query::CustomQueryDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct AuditLogQuery {
+    pub action: Option<ModerationAction>,
+    #[validate(range(min = 1, message = "actor_id must be greater than 0"))]
+    pub actor_id: Option<i32>,
+    #[validate(range(min = 1, message = "page must be greater than 0"))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 50, message = "limit must be between 1 and 50"))]
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct AuditLogUser {
+    pub id: i32,
+    pub username: String,
+}
+
+#[derive(Serialize)]
+pub struct AuditLogEntry {
+    pub id: i32,
+    pub action: ModerationAction,
+    pub actor: AuditLogUser,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<AuditLogUser>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<i32>,
+    pub reason: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<moderation_logs::Data> for AuditLogEntry {
+    fn from(value: moderation_logs::Data) -> Self {
+        let actor = value.actor.unwrap();
+        let target = value.target.flatten().map(|target| AuditLogUser {
+            id: target.id,
+            username: target.username,
+        });
+        Self {
+            id: value.id,
+            action: value.action,
+            actor: AuditLogUser {
+                id: actor.id,
+                username: actor.username,
+            },
+            target,
+            message_id: value.message_id,
+            reason: value.reason,
+            created_at: value.created_at.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AuditLogResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries: Option<Vec<AuditLogEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub struct ModerationRecord {
+    pub action: ModerationAction,
+    pub actor_id: i32,
+    pub room_id: i32,
+    pub target_id: Option<i32>,
+    pub message_id: Option<i32>,
+    pub reason: Option<String>,
+}
+
+// Callers ignore failures here, a missing log entry should never undo the action itself
+pub async fn record_action(
+    prisma_client: Arc<PrismaClient>,
+    record: ModerationRecord,
+) -> Result<moderation_logs::Data, prisma_client_rust::QueryError> {
+    let mut params = vec![
+        moderation_logs::reason::set(record.reason),
+        moderation_logs::message_id::set(record.message_id),
+    ];
+    if let Some(target_id) = record.target_id {
+        params.push(moderation_logs::target::connect(
+            user::UniqueWhereParam::IdEquals(target_id),
+        ));
+    }
+    prisma_client
+        .moderation_logs()
+        .create(
+            record.action,
+            user::UniqueWhereParam::IdEquals(record.actor_id),
+            rooms::UniqueWhereParam::IdEquals(record.room_id),
+            params,
+        )
+        .exec()
+        .await
+}
+
+pub async fn retrieve_audit_log(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Query(query), _): WithRejection<Query<AuditLogQuery>, CustomQueryDataRejection>,
+) -> (StatusCode, Json<AuditLogResponse>) {
+    match query.validate() {
+        Ok(_) => {
+            let mut filters = vec![moderation_logs::room_id::equals(participant.room_id)];
+            if let Some(action) = query.action {
+                filters.push(moderation_logs::action::equals(action));
+            }
+            if let Some(actor_id) = query.actor_id {
+                filters.push(moderation_logs::actor_id::equals(actor_id));
+            }
+            let limit = query.limit.unwrap_or(25);
+            let entries = state
+                .prisma_client
+                .moderation_logs()
+                .find_many(filters)
+                .with(moderation_logs::actor::fetch())
+                .with(moderation_logs::target::fetch())
+                .order_by(moderation_logs::created_at::order(
+                    prisma_client_rust::Direction::Desc,
+                ))
+                .skip((query.page.unwrap_or(1) - 1).saturating_mul(limit))
+                .take(limit)
+                .exec()
+                .await;
+            match entries {
+                Ok(entries) => (
+                    StatusCode::OK,
+                    Json(AuditLogResponse {
+                        success: true,
+                        http_code: 200,
+                        entries: Some(entries.into_iter().map(AuditLogEntry::from).collect()),
+                        validation_errors: None,
+                        error: None,
+                    }),
+                ),
+                Err(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(AuditLogResponse {
+                        success: false,
+                        http_code: 500,
+                        entries: None,
+                        validation_errors: None,
+                        error: Some("Internal server error".to_string()),
+                    }),
+                ),
+            }
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(AuditLogResponse {
+                    success: false,
+                    http_code: 422,
+                    entries: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
@@ -1,7 +1,12 @@
+use std::sync::Arc;
+
 use crate::{
-    error::validation_error::ValidationError,
-    prisma_client::client::{banned_users_room, rooms, user, users_rooms},
-    rejection::path::CustomPathDataRejection,
+    chat::messages::read_messages::clear_unread,
+    error::validation_error::{validate_all, ValidationError},
+    prisma_client::client::{
+        banned_users_room, rooms, user, users_rooms, ModerationAction, PrismaClient,
+    },
+    rejection::{json::OptionalJson, path::CustomPathDataRejection},
     shared::arc_clients::State as AppState,
     socket::interfaces::websocket_message::WebSocketMessage,
 };
@@ -11,9 +16,13 @@ use axum::{
     Extension, Json,
 };
 use axum_extra::extract::WithRejection;
+use prisma_client_rust::operator::or;
 use rustis::commands::PubSubCommands;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
+
+use super::audit_log::{record_action, ModerationRecord};
+
 #[derive(Serialize)]
 pub struct BanUserResponse {
     pub success: bool,
@@ -32,12 +41,63 @@ pub struct BanUserParams {
     pub user_id: i32,
 }
 
+#[derive(Default, Deserialize, Validate)]
+pub struct BanUserBody {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "reason must be between 1 and 255 characters"
+    ))]
+    pub reason: Option<String>,
+    // Duration in minutes, leaving it out makes the ban permanent
+    #[validate(range(
+        min = 1,
+        max = 525600,
+        message = "duration must be between 1 and 525600 minutes"
+    ))]
+    pub duration: Option<i64>,
+}
+
+// A ban is active when it has no expiry or the expiry lies in the future
+pub fn active_bans_filter(room_id: i32) -> Vec<banned_users_room::WhereParam> {
+    vec![
+        banned_users_room::room_id::equals(room_id),
+        or(vec![
+            banned_users_room::expires_at::equals(None),
+            banned_users_room::expires_at::gt(chrono::Utc::now().into()),
+        ]),
+    ]
+}
+
+pub fn active_ban_filter(user_id: i32, room_id: i32) -> Vec<banned_users_room::WhereParam> {
+    let mut filter = active_bans_filter(room_id);
+    filter.push(banned_users_room::user_id::equals(user_id));
+    filter
+}
+
+pub async fn is_banned(
+    prisma_client: Arc<PrismaClient>,
+    user_id: i32,
+    room_id: i32,
+) -> Result<bool, prisma_client_rust::QueryError> {
+    let ban = prisma_client
+        .banned_users_room()
+        .find_first(active_ban_filter(user_id, room_id))
+        .exec()
+        .await?;
+    Ok(ban.is_some())
+}
+
 pub async fn ban_user(
     State(state): State<AppState>,
     Extension(user): Extension<user::Data>,
+    Extension(participant): Extension<users_rooms::Data>,
     WithRejection(Path(params), _): WithRejection<Path<BanUserParams>, CustomPathDataRejection>,
+    // Optional so clients banning without a reason or duration can skip the body
+    OptionalJson(body): OptionalJson<BanUserBody>,
 ) -> (StatusCode, Json<BanUserResponse>) {
-    match params.validate() {
+    let body = body.unwrap_or_default();
+    match validate_all(&params, &body) {
         Ok(_) => {
             if params.user_id == user.id {
                 return (
@@ -54,7 +114,10 @@ pub async fn ban_user(
             let user = state
                 .prisma_client
                 .users_rooms()
-                .find_first(vec![users_rooms::user_id::equals(params.user_id)])
+                .find_first(vec![
+                    users_rooms::user_id::equals(params.user_id),
+                    users_rooms::room_id::equals(participant.room_id),
+                ])
                 .with(users_rooms::user::fetch())
                 .exec()
                 .await;
@@ -87,17 +150,10 @@ pub async fn ban_user(
                     )
                 }
             };
-            let is_already_banned = state
-                .prisma_client
-                .banned_users_room()
-                .find_first(vec![
-                    banned_users_room::user_id::equals(user.id),
-                    banned_users_room::room_id::equals(user.room_id),
-                ])
-                .exec()
-                .await;
+            let is_already_banned =
+                is_banned(state.prisma_client.clone(), user.user_id, user.room_id).await;
             let is_already_banned = match is_already_banned {
-                Ok(banned) => banned.is_some(),
+                Ok(banned) => banned,
                 Err(_) => {
                     return (
                         StatusCode::INTERNAL_SERVER_ERROR,
@@ -145,6 +201,37 @@ pub async fn ban_user(
                     );
                 }
             };
+            clear_unread(state.redis_client.clone(), user.user_id, user.room_id)
+                .await
+                .ok();
+            // Clear out expired bans so only one ban row exists per user and room
+            let clear_expired = state
+                .prisma_client
+                .banned_users_room()
+                .delete_many(vec![
+                    banned_users_room::user_id::equals(user.user_id),
+                    banned_users_room::room_id::equals(user.room_id),
+                ])
+                .exec()
+                .await;
+            match clear_expired {
+                Ok(_) => {}
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(BanUserResponse {
+                            success: false,
+                            http_code: 500,
+                            message: None,
+                            error: Some("Internal server error".to_string()),
+                            validation_errors: None,
+                        }),
+                    );
+                }
+            };
+            let expires_at = body
+                .duration
+                .map(|duration| (chrono::Utc::now() + chrono::Duration::minutes(duration)).into());
             // Insert ban
             let insert_ban = state
                 .prisma_client
@@ -152,13 +239,32 @@ pub async fn ban_user(
                 .create(
                     user::UniqueWhereParam::IdEquals(user.user_id),
                     rooms::UniqueWhereParam::IdEquals(user.room_id),
-                    vec![],
+                    vec![
+                        banned_users_room::reason::set(body.reason.clone()),
+                        banned_users_room::issuer::connect(user::UniqueWhereParam::IdEquals(
+                            participant.user_id,
+                        )),
+                        banned_users_room::expires_at::set(expires_at),
+                    ],
                 )
                 .exec()
                 .await;
 
             match insert_ban {
                 Ok(_) => {
+                    record_action(
+                        state.prisma_client.clone(),
+                        ModerationRecord {
+                            action: ModerationAction::Ban,
+                            actor_id: participant.user_id,
+                            room_id: user.room_id,
+                            target_id: Some(user.user_id),
+                            message_id: None,
+                            reason: body.reason,
+                        },
+                    )
+                    .await
+                    .ok();
                     state
                     .redis_client
                     .publish(
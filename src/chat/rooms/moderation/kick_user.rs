@@ -0,0 +1,273 @@
+use std::sync::Arc;
+
+use crate::{
+    chat::messages::read_messages::clear_unread,
+    error::validation_error::{validate_all, ValidationError},
+    prisma_client::client::{user, users_rooms, ModerationAction},
+    rejection::{json::CustomJsonDataRejection, path::CustomPathDataRejection},
+    shared::arc_clients::State as AppState,
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+use axum::{
+    extract::{Json as ExtractJson, Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use rustis::{
+    client::Client,
+    commands::{GenericCommands, PubSubCommands, StringCommands},
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::{
+    audit_log::{record_action, ModerationRecord},
+    ban_user::BanUserParams,
+};
+
+#[derive(Serialize)]
+pub struct KickUserResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct KickUserBody {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "reason must be between 1 and 255 characters"
+    ))]
+    pub reason: Option<String>,
+    // Cooldown in minutes before the user is allowed to rejoin
+    #[validate(range(
+        min = 1,
+        max = 10080,
+        message = "cooldown must be between 1 and 10080 minutes"
+    ))]
+    pub cooldown: Option<u64>,
+}
+
+// Returns the remaining cooldown in seconds when the user was recently kicked
+pub async fn kick_cooldown(
+    redis_client: Arc<Client>,
+    user_id: i32,
+    room_id: i32,
+) -> Result<Option<i64>, rustis::Error> {
+    let key = format!("kick_cooldown:{}:{}", room_id, user_id);
+    let ttl = redis_client.ttl(&key).await?;
+    if ttl > 0 {
+        Ok(Some(ttl))
+    } else {
+        Ok(None)
+    }
+}
+
+pub async fn kick_user(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Path(params), _): WithRejection<Path<BanUserParams>, CustomPathDataRejection>,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<KickUserBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<KickUserResponse>) {
+    match validate_all(&params, &body) {
+        Ok(_) => {
+            if params.user_id == user.id {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(KickUserResponse {
+                        success: false,
+                        http_code: 400,
+                        message: None,
+                        error: Some("You cannot kick yourself (owner)".to_string()),
+                        validation_errors: None,
+                    }),
+                );
+            }
+            let target = state
+                .prisma_client
+                .users_rooms()
+                .find_first(vec![
+                    users_rooms::user_id::equals(params.user_id),
+                    users_rooms::room_id::equals(participant.room_id),
+                ])
+                .exec()
+                .await;
+            let target = match target {
+                Ok(target) => {
+                    if target.is_none() {
+                        return (
+                            StatusCode::NOT_FOUND,
+                            Json(KickUserResponse {
+                                success: false,
+                                http_code: 404,
+                                message: None,
+                                error: Some("User is not a participant of this chat".to_string()),
+                                validation_errors: None,
+                            }),
+                        );
+                    }
+                    target.unwrap()
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(KickUserResponse {
+                            success: false,
+                            http_code: 500,
+                            message: None,
+                            error: Some("Internal server error".to_string()),
+                            validation_errors: None,
+                        }),
+                    )
+                }
+            };
+            let delete_participant = state
+                .prisma_client
+                .users_rooms()
+                .delete(users_rooms::UniqueWhereParam::IdEquals(target.id))
+                .exec()
+                .await;
+            match delete_participant {
+                Ok(_) => {}
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(KickUserResponse {
+                            success: false,
+                            http_code: 500,
+                            message: None,
+                            error: Some("Internal server error".to_string()),
+                            validation_errors: None,
+                        }),
+                    );
+                }
+            };
+            clear_unread(state.redis_client.clone(), target.user_id, target.room_id)
+                .await
+                .ok();
+            if let Some(cooldown) = body.cooldown {
+                let cooldown_set = state
+                    .redis_client
+                    .setex(
+                        format!("kick_cooldown:{}:{}", target.room_id, target.user_id),
+                        cooldown * 60,
+                        user.id,
+                    )
+                    .await;
+                match cooldown_set {
+                    Ok(_) => {}
+                    Err(_) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(KickUserResponse {
+                                success: false,
+                                http_code: 500,
+                                message: None,
+                                error: Some("Internal server error".to_string()),
+                                validation_errors: None,
+                            }),
+                        );
+                    }
+                };
+            }
+            record_action(
+                state.prisma_client.clone(),
+                ModerationRecord {
+                    action: ModerationAction::Kick,
+                    actor_id: participant.user_id,
+                    room_id: target.room_id,
+                    target_id: Some(target.user_id),
+                    message_id: None,
+                    reason: body.reason,
+                },
+            )
+            .await
+            .ok();
+            // Force the kicked user's socket out of the chat queue
+            state
+                .redis_client
+                .publish(
+                    format!("priv_user:{}", target.user_id),
+                    serde_json::to_string(&WebSocketMessage {
+                        record: Records::LeftQueue,
+                        queue: format!("chat:{}", target.room_id),
+                        data: serde_json::json!({
+                            "reason": "kick",
+                            "cooldown": body.cooldown,
+                        }),
+                    })
+                    .ok(),
+                )
+                .await
+                .ok();
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                state
+                    .redis_client
+                    .publish(
+                        format!("chat:{}", target.room_id),
+                        serde_json::to_string(&WebSocketMessage {
+                            record: Records::ParticipantLeft,
+                            queue: format!("chat:{}", target.room_id),
+                            data: serde_json::json!({
+                                "user_id": target.user_id,
+                            }),
+                        })
+                        .unwrap(),
+                    )
+                    .await
+                    .ok();
+            });
+            (
+                StatusCode::OK,
+                Json(KickUserResponse {
+                    success: true,
+                    http_code: 200,
+                    message: Some("User kicked".to_string()),
+                    error: None,
+                    validation_errors: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::BAD_REQUEST,
+                Json(KickUserResponse {
+                    success: false,
+                    http_code: 400,
+                    message: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
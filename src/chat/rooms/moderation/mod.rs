@@ -1,3 +1,8 @@
+pub mod archive_chat;
+pub mod audit_log;
 pub mod ban_user;
+pub mod kick_user;
+pub mod retrieve_bans;
+pub mod room_settings;
 pub mod transfer_ownership;
 pub mod unban_user;
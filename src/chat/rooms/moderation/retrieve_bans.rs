@@ -0,0 +1,175 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::Serialize;
+use validator::Validate;
+
+use crate::{
+    chat::interfaces::pagination_query::PaginationQuery,
+    error::validation_error::ValidationError,
+    prisma_client::client::{banned_users_room, users_rooms},
+    rejection::query::CustomQueryDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+use super::ban_user::active_bans_filter;
+
+#[derive(Serialize)]
+pub struct BannedUser {
+    pub id: i32,
+    pub username: String,
+}
+
+#[derive(Serialize)]
+pub struct Ban {
+    pub id: i32,
+    pub user: BannedUser,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<BannedUser>,
+    pub reason: Option<String>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<banned_users_room::Data> for Ban {
+    fn from(value: banned_users_room::Data) -> Self {
+        let user = value.user.unwrap();
+        let issuer = value.issuer.flatten().map(|issuer| BannedUser {
+            id: issuer.id,
+            username: issuer.username,
+        });
+        Self {
+            id: value.id,
+            user: BannedUser {
+                id: user.id,
+                username: user.username,
+            },
+            issuer,
+            reason: value.reason,
+            expires_at: value.expires_at.map(|expires_at| expires_at.into()),
+            created_at: value.created_at.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RetrieveBansResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bans: Option<Vec<Ban>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn retrieve_bans(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(Query(pagination), _): WithRejection<
+        Query<PaginationQuery>,
+        CustomQueryDataRejection,
+    >,
+) -> (StatusCode, Json<RetrieveBansResponse>) {
+    match pagination.validate() {
+        Ok(_) => {
+            // Expired bans are treated as lifted so they are left out
+            let active_bans = active_bans_filter(participant.room_id);
+            let total = state
+                .prisma_client
+                .banned_users_room()
+                .count(active_bans.clone())
+                .exec()
+                .await;
+            let total = match total {
+                Ok(total) => total,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(RetrieveBansResponse {
+                            success: false,
+                            http_code: 500,
+                            bans: None,
+                            total: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let bans = state
+                .prisma_client
+                .banned_users_room()
+                .find_many(active_bans)
+                .with(banned_users_room::user::fetch())
+                .with(banned_users_room::issuer::fetch())
+                .order_by(banned_users_room::created_at::order(
+                    prisma_client_rust::Direction::Desc,
+                ))
+                .skip(pagination.skip())
+                .take(pagination.take())
+                .exec()
+                .await;
+            match bans {
+                Ok(bans) => (
+                    StatusCode::OK,
+                    Json(RetrieveBansResponse {
+                        success: true,
+                        http_code: 200,
+                        bans: Some(bans.into_iter().map(Ban::from).collect()),
+                        total: Some(total),
+                        validation_errors: None,
+                        error: None,
+                    }),
+                ),
+                Err(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(RetrieveBansResponse {
+                        success: false,
+                        http_code: 500,
+                        bans: None,
+                        total: None,
+                        validation_errors: None,
+                        error: Some("Internal server error".to_string()),
+                    }),
+                ),
+            }
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(RetrieveBansResponse {
+                    success: false,
+                    http_code: 422,
+                    bans: None,
+                    total: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
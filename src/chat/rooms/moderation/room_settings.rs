@@ -0,0 +1,156 @@
+use axum::{
+    extract::{Json as ExtractJson, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{rooms, users_rooms, JoinPolicy},
+    rejection::json::CustomJsonDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct RoomSettingsBody {
+    #[validate(range(
+        min = 1,
+        max = 100,
+        message = "message_limit must be between 1 and 100"
+    ))]
+    pub message_limit: Option<i32>,
+    #[validate(range(
+        min = 1,
+        max = 3600,
+        message = "message_window must be between 1 and 3600 seconds"
+    ))]
+    pub message_window: Option<i32>,
+    #[validate(range(
+        min = 0,
+        max = 21600,
+        message = "slow_mode must be between 0 and 21600 seconds"
+    ))]
+    pub slow_mode: Option<i32>,
+    pub join_policy: Option<JoinPolicy>,
+}
+
+#[derive(Serialize)]
+pub struct RoomSettings {
+    pub message_limit: i32,
+    pub message_window: i32,
+    pub slow_mode: i32,
+    pub join_policy: JoinPolicy,
+}
+
+impl From<rooms::Data> for RoomSettings {
+    fn from(value: rooms::Data) -> Self {
+        Self {
+            message_limit: value.message_limit,
+            message_window: value.message_window,
+            slow_mode: value.slow_mode,
+            join_policy: value.join_policy,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RoomSettingsResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<RoomSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn update_room_settings(
+    State(state): State<AppState>,
+    Extension(participant): Extension<users_rooms::Data>,
+    WithRejection(ExtractJson(body), _): WithRejection<
+        ExtractJson<RoomSettingsBody>,
+        CustomJsonDataRejection,
+    >,
+) -> (StatusCode, Json<RoomSettingsResponse>) {
+    match body.validate() {
+        Ok(_) => {
+            let mut settings = vec![];
+            if let Some(message_limit) = body.message_limit {
+                settings.push(rooms::message_limit::set(message_limit));
+            }
+            if let Some(message_window) = body.message_window {
+                settings.push(rooms::message_window::set(message_window));
+            }
+            if let Some(slow_mode) = body.slow_mode {
+                settings.push(rooms::slow_mode::set(slow_mode));
+            }
+            if let Some(join_policy) = body.join_policy {
+                settings.push(rooms::join_policy::set(join_policy));
+            }
+            let room = state
+                .prisma_client
+                .rooms()
+                .update(
+                    rooms::UniqueWhereParam::IdEquals(participant.room_id),
+                    settings,
+                )
+                .exec()
+                .await;
+            match room {
+                Ok(room) => (
+                    StatusCode::OK,
+                    Json(RoomSettingsResponse {
+                        success: true,
+                        http_code: 200,
+                        settings: Some(room.into()),
+                        validation_errors: None,
+                        error: None,
+                    }),
+                ),
+                Err(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(RoomSettingsResponse {
+                        success: false,
+                        http_code: 500,
+                        settings: None,
+                        validation_errors: None,
+                        error: Some("Internal server error".to_string()),
+                    }),
+                ),
+            }
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(RoomSettingsResponse {
+                    success: false,
+                    http_code: 422,
+                    settings: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
@@ -9,12 +9,15 @@ use validator::Validate;
 
 use crate::{
     error::validation_error::ValidationError,
-    prisma_client::client::{banned_users_room, user, users_rooms},
+    prisma_client::client::{banned_users_room, user, users_rooms, ModerationAction},
     rejection::path::CustomPathDataRejection,
     shared::arc_clients::State as AppState,
 };
 
-use super::ban_user::BanUserParams;
+use super::{
+    audit_log::{record_action, ModerationRecord},
+    ban_user::{active_ban_filter, BanUserParams},
+};
 
 #[derive(Serialize)]
 pub struct UnbanUserErrorResponse {
@@ -38,9 +41,10 @@ pub async fn unban_user(
                 .prisma_client
                 .user()
                 .find_unique(user::UniqueWhereParam::IdEquals(user_id))
-                .with(user::banned_users_room::fetch(vec![
-                    banned_users_room::room_id::equals(participant.room_id),
-                ]))
+                .with(user::banned_users_room::fetch(active_ban_filter(
+                    user_id,
+                    participant.room_id,
+                )))
                 .exec()
                 .await;
             let user = match user {
@@ -95,7 +99,7 @@ pub async fn unban_user(
                     }),
                 ));
             }
-            let user = state
+            let unban = state
                 .prisma_client
                 .banned_users_room()
                 .delete(banned_users_room::UniqueWhereParam::IdEquals(
@@ -103,8 +107,23 @@ pub async fn unban_user(
                 ))
                 .exec()
                 .await;
-            match user {
-                Ok(_) => Ok(StatusCode::NO_CONTENT),
+            match unban {
+                Ok(_) => {
+                    record_action(
+                        state.prisma_client.clone(),
+                        ModerationRecord {
+                            action: ModerationAction::Unban,
+                            actor_id: participant.user_id,
+                            room_id: participant.room_id,
+                            target_id: Some(user_id),
+                            message_id: None,
+                            reason: None,
+                        },
+                    )
+                    .await
+                    .ok();
+                    Ok(StatusCode::NO_CONTENT)
+                }
                 Err(_) => {
                     return Err((
                         StatusCode::INTERNAL_SERVER_ERROR,
@@ -1,19 +1,21 @@
 use crate::{
+    chat::interfaces::pagination_query::PaginationQuery,
     prisma_client::client::{
         rooms::{self, Data as Room},
         user::Data as User,
-        users_rooms,
+        users_rooms, RoomKind,
     },
-    rejection::path::CustomPathDataRejection,
+    rejection::{path::CustomPathDataRejection, query::CustomQueryDataRejection},
     shared::arc_clients::State as AppState,
 };
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
 use axum_extra::extract::WithRejection;
 use serde::Serialize;
+use validator::Validate;
 
 use super::interfaces::params_chat::RetrieveChatParams;
 
@@ -34,26 +36,30 @@ impl From<User> for ParticipantChat {
     }
 }
 
-impl From<Room> for Chat {
-    fn from(value: Room) -> Self {
+impl Chat {
+    // `value` only carries the requested page of participants, the total is counted separately
+    pub fn new(value: Room, participant_count: i64) -> Self {
         let participants: Vec<ParticipantChat> = value
             .users_rooms()
             .unwrap()
             .into_iter()
             .map(|participant| participant.user().unwrap().to_owned().into())
             .collect();
-        return Chat {
+        Chat {
             name: value.name,
             capacity: value.capacity.try_into().unwrap(),
+            participant_count,
             users: participants,
-        };
+        }
     }
 }
 
 #[derive(Serialize)]
 pub struct Chat {
     pub name: String,
-    pub capacity: u8,
+    // 0 means the room has no participant limit
+    pub capacity: u32,
+    pub participant_count: i64,
     pub users: Vec<ParticipantChat>,
 }
 
@@ -73,20 +79,51 @@ pub async fn retrieve_chat(
         Path<RetrieveChatParams>,
         CustomPathDataRejection,
     >,
+    WithRejection(Query(pagination), _): WithRejection<
+        Query<PaginationQuery>,
+        CustomQueryDataRejection,
+    >,
 ) -> (StatusCode, Json<RetrieveChatResonse>) {
-    let chat_id = chat_param.id;
+    if chat_param.id > i32::MAX as u64 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(RetrieveChatResonse {
+                success: false,
+                http_code: 400,
+                chat: None,
+                error: Some("Chat id cannot exceed 32 bits signed".to_string()),
+            }),
+        );
+    }
+    if pagination.validate().is_err() {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(RetrieveChatResonse {
+                success: false,
+                http_code: 422,
+                chat: None,
+                error: Some("page must be greater than 0 and limit between 1 and 50".to_string()),
+            }),
+        );
+    }
+    let chat_id = chat_param.id as i32;
     let chat = state
         .prisma_client
         .rooms()
-        .find_unique(rooms::UniqueWhereParam::IdEquals(
-            chat_id.try_into().unwrap(),
-        ))
-        .with(rooms::users_rooms::fetch(vec![]).with(users_rooms::user::fetch()))
+        .find_unique(rooms::UniqueWhereParam::IdEquals(chat_id))
+        .with(
+            rooms::users_rooms::fetch(vec![])
+                .order_by(users_rooms::id::order(prisma_client_rust::Direction::Asc))
+                .skip(pagination.skip())
+                .take(pagination.take())
+                .with(users_rooms::user::fetch()),
+        )
         .exec()
         .await;
     let chat = match chat {
         Ok(chat) => {
-            if chat.is_none() {
+            // Direct and group conversations are only listed through /chat/direct
+            if chat.is_none() || chat.as_ref().unwrap().kind != RoomKind::Group {
                 return (
                     StatusCode::NOT_FOUND,
                     Json(RetrieveChatResonse {
@@ -111,12 +148,32 @@ pub async fn retrieve_chat(
             );
         }
     };
+    let participant_count = state
+        .prisma_client
+        .users_rooms()
+        .count(vec![users_rooms::room_id::equals(chat_id)])
+        .exec()
+        .await;
+    let participant_count = match participant_count {
+        Ok(participant_count) => participant_count,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RetrieveChatResonse {
+                    success: false,
+                    http_code: 500,
+                    chat: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            );
+        }
+    };
     (
         StatusCode::OK,
         Json(RetrieveChatResonse {
             success: true,
             http_code: 200,
-            chat: Some(chat.into()),
+            chat: Some(Chat::new(chat, participant_count)),
             error: None,
         }),
     )
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use rustis::commands::HashCommands;
+use serde::Serialize;
+
+use crate::{
+    chat::messages::read_messages::{count_unread, store_unread, unread_key, unread_mentions_key},
+    prisma_client::client::{user, users_rooms},
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Serialize)]
+pub struct UnreadCount {
+    pub chat_id: i32,
+    pub unread: i64,
+    pub mentions: i64,
+}
+
+#[derive(Serialize)]
+pub struct RetrieveUnreadResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chats: Option<Vec<UnreadCount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn retrieve_unread(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+) -> (StatusCode, Json<RetrieveUnreadResponse>) {
+    let participations = state
+        .prisma_client
+        .users_rooms()
+        .find_many(vec![users_rooms::user_id::equals(user.id)])
+        .exec()
+        .await;
+    let participations = match participations {
+        Ok(participations) => participations,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(RetrieveUnreadResponse {
+                    success: false,
+                    http_code: 500,
+                    chats: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    // Missing counters (new rooms, flushed Redis) are seeded from the database
+    let mut unread: HashMap<String, i64> = state
+        .redis_client
+        .hgetall(unread_key(user.id))
+        .await
+        .unwrap_or_default();
+    let mut unread_mentions: HashMap<String, i64> = state
+        .redis_client
+        .hgetall(unread_mentions_key(user.id))
+        .await
+        .unwrap_or_default();
+    let mut chats = Vec::with_capacity(participations.len());
+    for participation in &participations {
+        let room_id = participation.room_id.to_string();
+        let counts = match (unread.remove(&room_id), unread_mentions.remove(&room_id)) {
+            (Some(unread), Some(mentions)) => (unread, mentions),
+            _ => {
+                let counts = count_unread(state.prisma_client.clone(), participation).await;
+                let counts = match counts {
+                    Ok(counts) => counts,
+                    Err(_) => {
+                        return (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(RetrieveUnreadResponse {
+                                success: false,
+                                http_code: 500,
+                                chats: None,
+                                error: Some("Internal server error".to_string()),
+                            }),
+                        )
+                    }
+                };
+                store_unread(state.redis_client.clone(), participation, counts)
+                    .await
+                    .ok();
+                counts
+            }
+        };
+        chats.push(UnreadCount {
+            chat_id: participation.room_id,
+            unread: counts.0,
+            mentions: counts.1,
+        });
+    }
+    // Whatever is left belongs to rooms the user no longer takes part in
+    let stale: Vec<String> = unread.into_keys().collect();
+    if !stale.is_empty() {
+        state
+            .redis_client
+            .hdel(unread_key(user.id), stale)
+            .await
+            .ok();
+    }
+    let stale: Vec<String> = unread_mentions.into_keys().collect();
+    if !stale.is_empty() {
+        state
+            .redis_client
+            .hdel(unread_mentions_key(user.id), stale)
+            .await
+            .ok();
+    }
+    (
+        StatusCode::OK,
+        Json(RetrieveUnreadResponse {
+            success: true,
+            http_code: 200,
+            chats: Some(chats),
+            error: None,
+        }),
+    )
+}
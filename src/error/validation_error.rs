@@ -5,3 +5,18 @@ pub struct ValidationError {
     pub field: String,
     pub messages: Vec<String>,
 }
+
+// Validates both parts of a request so errors of one don't hide the other's
+pub fn validate_all(
+    params: &impl validator::Validate,
+    body: &impl validator::Validate,
+) -> Result<(), validator::ValidationErrors> {
+    match (params.validate(), body.validate()) {
+        (Ok(_), Ok(_)) => Ok(()),
+        (Err(errors), Ok(_)) | (Ok(_), Err(errors)) => Err(errors),
+        (Err(mut errors), Err(body_errors)) => {
+            errors.errors_mut().extend(body_errors.into_errors());
+            Err(errors)
+        }
+    }
+}
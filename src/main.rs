@@ -2,9 +2,19 @@ use std::{net::ToSocketAddrs, sync::Arc};
 
 use axum::{error_handling::HandleErrorLayer, BoxError, Router};
 use chat_app_rust::{
-    chat::chat_router::chat_general_router, error::default_error::default_error,
-    governor::display_error::display_error, prisma_client::client::PrismaClient,
-    shared::arc_clients::State, socket::websocket_router::websocket_router,
+    chat::{
+        chat_router::chat_general_router,
+        invites::expire_invites::spawn_invite_sweep,
+        messages::{
+            deliver_scheduled::spawn_scheduler,
+            process_attachment::{spawn_image_worker, IMAGE_QUEUE_SIZE},
+        },
+    },
+    error::default_error::default_error,
+    governor::display_error::display_error,
+    prisma_client::client::PrismaClient,
+    shared::{arc_clients::State, storage::storage_from_env},
+    socket::websocket_router::websocket_router,
     users::users_router::users_router,
 };
 use tower::ServiceBuilder;
@@ -13,6 +23,8 @@ use tower_governor::{governor::GovernorConfigBuilder, GovernorLayer};
 
 #[tokio::main]
 async fn main() {
+    dotenv::dotenv().ok();
+    let (image_jobs, image_queue) = tokio::sync::mpsc::channel(IMAGE_QUEUE_SIZE);
     let state = State {
         prisma_client: Arc::new(
             PrismaClient::_builder()
@@ -25,8 +37,14 @@ async fn main() {
                 .await
                 .expect("Failed to construct Redis Client"),
         ),
+        storage: storage_from_env(),
+        image_jobs,
     };
 
+    spawn_invite_sweep(state.clone());
+    spawn_image_worker(state.clone(), image_queue);
+    spawn_scheduler(state.clone());
+
     let governor = Box::new(
         GovernorConfigBuilder::default()
             .per_millisecond(800)
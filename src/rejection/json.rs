@@ -1,4 +1,12 @@
-use axum::{extract::rejection::JsonRejection, http::StatusCode, response::IntoResponse};
+use axum::{
+    async_trait,
+    body::HttpBody,
+    extract::{rejection::JsonRejection, FromRequest, Json},
+    http::{header, Request, StatusCode},
+    response::IntoResponse,
+    BoxError,
+};
+use serde::de::DeserializeOwned;
 
 use super::error_format::RejectionResponseError;
 
@@ -43,3 +51,32 @@ impl IntoResponse for CustomJsonDataRejection {
             .into_response()
     }
 }
+
+// JSON body that may be left out entirely. Only a request without a body yields `None`,
+// a body that is present still has to parse and is rejected like `Json` otherwise
+pub struct OptionalJson<T>(pub Option<T>);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for OptionalJson<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = CustomJsonDataRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let has_body = req.headers().contains_key(header::TRANSFER_ENCODING)
+            || req
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .map_or(false, |length| length != "0");
+        if !has_body {
+            return Ok(OptionalJson(None));
+        }
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        Ok(OptionalJson(Some(value)))
+    }
+}
@@ -1,3 +1,4 @@
 pub mod error_format;
 pub mod json;
 pub mod path;
+pub mod query;
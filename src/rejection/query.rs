@@ -0,0 +1,36 @@
+use axum::{extract::rejection::QueryRejection, http::StatusCode, response::IntoResponse};
+use serde::Serialize;
+
+use super::error_format::RejectionResponseError;
+
+#[derive(Serialize)]
+pub struct CustomQueryDataRejection {
+    pub message: String,
+}
+
+impl From<QueryRejection> for CustomQueryDataRejection {
+    fn from(value: QueryRejection) -> Self {
+        match value {
+            QueryRejection::FailedToDeserializeQueryString(err) => CustomQueryDataRejection {
+                message: err.to_string(),
+            },
+            _ => CustomQueryDataRejection {
+                message: "Unknown error".to_string(),
+            },
+        }
+    }
+}
+
+impl IntoResponse for CustomQueryDataRejection {
+    fn into_response(self) -> axum::response::Response {
+        (
+            StatusCode::BAD_REQUEST,
+            axum::Json(RejectionResponseError {
+                success: false,
+                http_code: 400,
+                error: self.message,
+            }),
+        )
+            .into_response()
+    }
+}
@@ -1,10 +1,15 @@
 use std::sync::Arc;
 
-use crate::prisma_client::client::PrismaClient;
+use crate::{chat::messages::process_attachment::ImageJob, prisma_client::client::PrismaClient};
+
+use super::storage::Storage;
 
 #[derive(Clone)]
 pub struct State {
     pub prisma_client: Arc<PrismaClient>,
     // For now sugar coat it with option we'll implement it later
     pub redis_client: Arc<rustis::client::Client>,
+    pub storage: Arc<dyn Storage>,
+    // Queue of the background image worker
+    pub image_jobs: tokio::sync::mpsc::Sender<ImageJob>,
 }
@@ -0,0 +1,30 @@
+use once_cell::sync::Lazy;
+
+pub struct AttachmentLimits {
+    pub max_size: usize,
+    pub allowed_types: Vec<String>,
+}
+
+impl AttachmentLimits {
+    pub fn allows(&self, mime_type: &str) -> bool {
+        self.allowed_types
+            .iter()
+            .any(|allowed_type| allowed_type == mime_type)
+    }
+}
+
+// Read once from `ATTACHMENT_MAX_BYTES` and `ATTACHMENT_MIME_TYPES` (comma separated)
+pub static ATTACHMENT_LIMITS: Lazy<AttachmentLimits> = Lazy::new(|| AttachmentLimits {
+    max_size: std::env::var("ATTACHMENT_MAX_BYTES")
+        .ok()
+        .and_then(|max_size| max_size.parse().ok())
+        .unwrap_or(10 * 1024 * 1024),
+    allowed_types: std::env::var("ATTACHMENT_MIME_TYPES")
+        .unwrap_or_else(|_| {
+            "image/png,image/jpeg,image/gif,image/webp,application/pdf,text/plain".to_string()
+        })
+        .split(',')
+        .map(|allowed_type| allowed_type.trim().to_string())
+        .filter(|allowed_type| !allowed_type.is_empty())
+        .collect(),
+});
@@ -0,0 +1,47 @@
+use once_cell::sync::Lazy;
+
+use crate::prisma_client::client::{invites, InviteState};
+
+pub struct InviteExpiry {
+    pub ttl_minutes: i64,
+    pub sweep_interval: u64,
+}
+
+impl InviteExpiry {
+    // Invites created before this moment are past their lifetime
+    pub fn cutoff(&self) -> chrono::DateTime<chrono::FixedOffset> {
+        (chrono::Utc::now() - chrono::Duration::minutes(self.ttl_minutes)).into()
+    }
+
+    pub fn is_expired(&self, created_at: chrono::DateTime<chrono::FixedOffset>) -> bool {
+        created_at <= self.cutoff()
+    }
+
+    pub fn active_filter(&self) -> Vec<invites::WhereParam> {
+        vec![
+            invites::state::equals(InviteState::Pending),
+            invites::created_at::gt(self.cutoff()),
+        ]
+    }
+
+    pub fn stale_filter(&self) -> Vec<invites::WhereParam> {
+        vec![
+            invites::state::equals(InviteState::Pending),
+            invites::created_at::lte(self.cutoff()),
+        ]
+    }
+}
+
+// Read once from `INVITE_TTL_MINUTES` and `INVITE_SWEEP_SECONDS`
+pub static INVITE_EXPIRY: Lazy<InviteExpiry> = Lazy::new(|| InviteExpiry {
+    ttl_minutes: std::env::var("INVITE_TTL_MINUTES")
+        .ok()
+        .and_then(|ttl_minutes| ttl_minutes.parse().ok())
+        .unwrap_or(1440),
+    sweep_interval: std::env::var("INVITE_SWEEP_SECONDS")
+        .ok()
+        .and_then(|sweep_interval| sweep_interval.parse().ok())
+        // A zero period would panic the sweep's interval
+        .map(|sweep_interval: u64| sweep_interval.max(1))
+        .unwrap_or(60),
+});
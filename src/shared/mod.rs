@@ -1 +1,6 @@
 pub mod arc_clients;
+pub mod attachment_limits;
+pub mod invite_expiry;
+pub mod room_limits;
+pub mod scheduled_messages;
+pub mod storage;
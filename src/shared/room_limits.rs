@@ -0,0 +1,44 @@
+use once_cell::sync::Lazy;
+
+// A capacity of 0 stores a room without a participant limit
+pub const UNLIMITED_CAPACITY: u32 = 0;
+
+pub struct RoomLimits {
+    pub max_capacity: u32,
+    pub allow_unlimited: bool,
+    pub max_pinned: i64,
+    pub max_group_members: i64,
+    pub max_distinct_reactions: usize,
+}
+
+impl RoomLimits {
+    pub fn is_full(capacity: i32, participants: i64) -> bool {
+        capacity != UNLIMITED_CAPACITY as i32 && participants >= capacity as i64
+    }
+}
+
+// Read once from `ROOM_MAX_CAPACITY`, `ROOM_ALLOW_UNLIMITED`, `ROOM_MAX_PINNED`,
+// `GROUP_DIRECT_MAX_MEMBERS` and `MESSAGE_MAX_REACTIONS`
+pub static ROOM_LIMITS: Lazy<RoomLimits> = Lazy::new(|| RoomLimits {
+    max_capacity: std::env::var("ROOM_MAX_CAPACITY")
+        .ok()
+        .and_then(|max_capacity| max_capacity.parse().ok())
+        // Capacities are stored as a signed 32 bit column
+        .map(|max_capacity: u32| max_capacity.min(i32::MAX as u32))
+        .unwrap_or(10),
+    allow_unlimited: std::env::var("ROOM_ALLOW_UNLIMITED")
+        .map(|allow_unlimited| allow_unlimited == "true")
+        .unwrap_or(false),
+    max_pinned: std::env::var("ROOM_MAX_PINNED")
+        .ok()
+        .and_then(|max_pinned| max_pinned.parse().ok())
+        .unwrap_or(25),
+    max_group_members: std::env::var("GROUP_DIRECT_MAX_MEMBERS")
+        .ok()
+        .and_then(|max_group_members| max_group_members.parse().ok())
+        .unwrap_or(8),
+    max_distinct_reactions: std::env::var("MESSAGE_MAX_REACTIONS")
+        .ok()
+        .and_then(|max_distinct_reactions| max_distinct_reactions.parse().ok())
+        .unwrap_or(20),
+});
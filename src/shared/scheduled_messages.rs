@@ -0,0 +1,48 @@
+use once_cell::sync::Lazy;
+
+pub struct ScheduledMessages {
+    pub poll_interval: u64,
+    pub claim_timeout_seconds: i64,
+    pub batch_size: i64,
+    pub max_pending: i64,
+    pub max_days_ahead: i64,
+}
+
+impl ScheduledMessages {
+    // Claims taken before this moment belong to an instance that died mid delivery
+    pub fn stale_claim_cutoff(&self) -> chrono::DateTime<chrono::FixedOffset> {
+        (chrono::Utc::now() - chrono::Duration::seconds(self.claim_timeout_seconds)).into()
+    }
+
+    pub fn is_valid_send_at(&self, send_at: chrono::DateTime<chrono::Utc>) -> bool {
+        let now = chrono::Utc::now();
+        send_at > now && send_at <= now + chrono::Duration::days(self.max_days_ahead)
+    }
+}
+
+// Read once from `SCHEDULER_POLL_SECONDS`, `SCHEDULER_CLAIM_SECONDS`, `SCHEDULER_BATCH_SIZE`,
+// `SCHEDULED_MAX_PENDING` and `SCHEDULED_MAX_DAYS`
+pub static SCHEDULED_MESSAGES: Lazy<ScheduledMessages> = Lazy::new(|| ScheduledMessages {
+    poll_interval: std::env::var("SCHEDULER_POLL_SECONDS")
+        .ok()
+        .and_then(|poll_interval| poll_interval.parse().ok())
+        // A zero period would panic the scheduler's interval
+        .map(|poll_interval: u64| poll_interval.max(1))
+        .unwrap_or(5),
+    claim_timeout_seconds: std::env::var("SCHEDULER_CLAIM_SECONDS")
+        .ok()
+        .and_then(|claim_timeout_seconds| claim_timeout_seconds.parse().ok())
+        .unwrap_or(60),
+    batch_size: std::env::var("SCHEDULER_BATCH_SIZE")
+        .ok()
+        .and_then(|batch_size| batch_size.parse().ok())
+        .unwrap_or(50),
+    max_pending: std::env::var("SCHEDULED_MAX_PENDING")
+        .ok()
+        .and_then(|max_pending| max_pending.parse().ok())
+        .unwrap_or(25),
+    max_days_ahead: std::env::var("SCHEDULED_MAX_DAYS")
+        .ok()
+        .and_then(|max_days_ahead| max_days_ahead.parse().ok())
+        .unwrap_or(365),
+});
@@ -0,0 +1,42 @@
+pub mod local;
+pub mod s3;
+
+use std::{fmt::Display, sync::Arc};
+
+use async_trait::async_trait;
+
+use self::{local::LocalStorage, s3::S3Storage};
+
+pub enum StorageError {
+    NotFound,
+    Io(std::io::Error),
+    Remote(String),
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::NotFound => write!(f, "Object not found"),
+            StorageError::Io(e) => write!(f, "Io error: {}", e),
+            StorageError::Remote(e) => write!(f, "Remote storage error: {}", e),
+        }
+    }
+}
+
+// Where attachment bytes live, keys are generated by the server and never come from users
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<(), StorageError>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+// `STORAGE_BACKEND` picks the implementation, local is the default
+pub fn storage_from_env() -> Arc<dyn Storage> {
+    match std::env::var("STORAGE_BACKEND").as_deref() {
+        Ok("s3") => Arc::new(S3Storage::from_env().expect("Failed to construct S3 storage")),
+        _ => Arc::new(LocalStorage::new(
+            std::env::var("STORAGE_LOCAL_DIR").unwrap_or_else(|_| "uploads".to_string()),
+        )),
+    }
+}
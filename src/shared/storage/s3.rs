@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use s3::{creds::Credentials, error::S3Error, Bucket, Region};
+
+use super::{Storage, StorageError};
+
+// Works with any S3 compatible service, a custom endpoint with path style urls
+// covers local stand-ins such as MinIO
+pub struct S3Storage {
+    bucket: Bucket,
+}
+
+impl S3Storage {
+    // Reads `S3_BUCKET`, `S3_REGION`, `S3_ENDPOINT`, `S3_ACCESS_KEY` and `S3_SECRET_KEY`
+    pub fn from_env() -> Result<Self, S3Error> {
+        let region = std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        let region = match std::env::var("S3_ENDPOINT") {
+            Ok(endpoint) => Region::Custom { region, endpoint },
+            Err(_) => region.parse()?,
+        };
+        let credentials = Credentials::new(
+            std::env::var("S3_ACCESS_KEY").ok().as_deref(),
+            std::env::var("S3_SECRET_KEY").ok().as_deref(),
+            None,
+            None,
+            None,
+        )?;
+        let bucket = Bucket::new(
+            &std::env::var("S3_BUCKET").unwrap_or_else(|_| "attachments".to_string()),
+            region,
+            credentials,
+        )?
+        .with_path_style();
+        Ok(Self { bucket })
+    }
+}
+
+fn map_status(code: u16) -> Result<(), StorageError> {
+    match code {
+        200..=299 => Ok(()),
+        404 => Err(StorageError::NotFound),
+        code => Err(StorageError::Remote(format!("Unexpected status {}", code))),
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<(), StorageError> {
+        let response = self
+            .bucket
+            .put_object_with_content_type(key, &bytes, content_type)
+            .await
+            .map_err(|e| StorageError::Remote(e.to_string()))?;
+        map_status(response.status_code())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let response = self
+            .bucket
+            .get_object(key)
+            .await
+            .map_err(|e| StorageError::Remote(e.to_string()))?;
+        map_status(response.status_code())?;
+        Ok(response.bytes().to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let response = self
+            .bucket
+            .delete_object(key)
+            .await
+            .map_err(|e| StorageError::Remote(e.to_string()))?;
+        match map_status(response.status_code()) {
+            Ok(_) | Err(StorageError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use std::{collections::HashSet, fmt::Display};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    time::Instant,
+};
 
 use axum::extract::ws::{Message, WebSocket};
 use futures::SinkExt;
@@ -6,12 +10,15 @@ use futures_util::stream::SplitSink;
 use rustis::client::PubSubStream;
 
 use crate::{
-    prisma_client::client::{rooms, user, PrismaClient},
+    prisma_client::client::{rooms, user},
+    shared::arc_clients::State as AppState,
     socket::interfaces::{
         websocket_incoming_message::IncomingWebsocketMessage, websocket_message::WebSocketMessage,
     },
 };
 
+use super::typing::handle_typing;
+
 pub enum MessageHandlerError {
     RedisError(rustis::Error),
     JsonError(serde_json::Error),
@@ -39,9 +46,11 @@ pub async fn handle_incoming_message(
     user_id: &u64,
     pubsub: &mut PubSubStream,
     sender: &mut SplitSink<WebSocket, Message>,
-    prisma_client: &PrismaClient,
+    state: &AppState,
     subbed_channels: &mut HashSet<String>,
+    last_typing: &mut HashMap<i32, Instant>,
 ) -> Result<(), MessageHandlerError> {
+    let prisma_client = &state.prisma_client;
     let text = message.to_text().map_err(MessageHandlerError::AxumError)?;
     let message: IncomingWebsocketMessage =
         serde_json::from_str(text).map_err(MessageHandlerError::JsonError)?;
@@ -247,5 +256,27 @@ pub async fn handle_incoming_message(
         crate::socket::interfaces::websocket_message::Records::RateLimit => Ok(()),
         crate::socket::interfaces::websocket_message::Records::ParticipantJoined => Ok(()),
         crate::socket::interfaces::websocket_message::Records::ParticipantLeft => Ok(()),
+        crate::socket::interfaces::websocket_message::Records::TypingStart
+        | crate::socket::interfaces::websocket_message::Records::TypingStop => {
+            match message.mount {
+                crate::socket::interfaces::websocket_incoming_message::Mounts::Chat => {
+                    handle_typing(
+                        message.record,
+                        &message.queue,
+                        *user_id as i32,
+                        state.redis_client.clone(),
+                        prisma_client,
+                        subbed_channels,
+                        last_typing,
+                    )
+                    .await
+                }
+                crate::socket::interfaces::websocket_incoming_message::Mounts::User => {
+                    Err(MessageHandlerError::InvalidMessage(
+                        "Typing is only available on chat mounts".to_string(),
+                    ))
+                }
+            }
+        }
     }
 }
@@ -1,4 +1,5 @@
 pub mod incoming_user_message;
 pub mod private_message_handler;
 pub mod ratelimit;
+pub mod typing;
 pub mod websocket_primary_handler;
@@ -0,0 +1,147 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use rustis::{
+    client::Client,
+    commands::{CallBuilder, GenericCommands, PubSubCommands, ScriptingCommands, StringCommands},
+};
+
+use crate::{
+    prisma_client::client::{users_rooms, PrismaClient},
+    socket::interfaces::websocket_message::{Records, WebSocketMessage},
+};
+
+use super::incoming_user_message::MessageHandlerError;
+
+// Clients repeat typing_start while the user keeps typing, repeats closer than this are dropped
+const TYPING_THROTTLE: Duration = Duration::from_secs(2);
+// Without a refresh or a typing_stop the server clears the indicator after this long
+const TYPING_TTL_SECONDS: u64 = 6;
+
+// Compare and delete in one step, a start arriving between a GET and a DEL would be wiped
+const EXPIRE_TYPING_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+fn typing_key(room_id: i32, user_id: i32) -> String {
+    format!("typing:{}:{}", room_id, user_id)
+}
+
+async fn publish_typing(
+    redis_client: &Client,
+    room_id: i32,
+    user_id: i32,
+    record: Records,
+) -> Result<(), rustis::Error> {
+    redis_client
+        .publish(
+            format!("chat:{}", room_id),
+            WebSocketMessage {
+                record,
+                queue: format!("chat:{}", room_id),
+                data: serde_json::json!({
+                    "user_id": user_id,
+                }),
+            }
+            .to_string(),
+        )
+        .await?;
+    Ok(())
+}
+
+// Typing state only lives in Redis and on the wire, nothing is stored in the database
+pub async fn handle_typing(
+    record: Records,
+    queue: &str,
+    user_id: i32,
+    redis_client: Arc<Client>,
+    prisma_client: &PrismaClient,
+    subbed_channels: &HashSet<String>,
+    last_typing: &mut HashMap<i32, Instant>,
+) -> Result<(), MessageHandlerError> {
+    let room_id = match queue.parse::<i32>() {
+        Ok(room_id) => room_id,
+        Err(_) => {
+            return Err(MessageHandlerError::InvalidMessage(
+                "Invalid queue id".to_string(),
+            ))
+        }
+    };
+    if !subbed_channels.contains(&format!("chat:{}", room_id)) {
+        return Err(MessageHandlerError::ValidationError(
+            "Subscribe to the queue before sending typing events".to_string(),
+        ));
+    }
+    let started = matches!(record, Records::TypingStart);
+    if started {
+        if let Some(last) = last_typing.get(&room_id) {
+            if last.elapsed() < TYPING_THROTTLE {
+                return Ok(());
+            }
+        }
+    } else if last_typing.remove(&room_id).is_none() {
+        // Nothing was started from this connection, there is no indicator to clear
+        return Ok(());
+    }
+    let participant = prisma_client
+        .users_rooms()
+        .find_first(vec![
+            users_rooms::user_id::equals(user_id),
+            users_rooms::room_id::equals(room_id),
+        ])
+        .exec()
+        .await
+        .map_err(MessageHandlerError::ServerError)?;
+    if participant.is_none() {
+        return Err(MessageHandlerError::ValidationError(
+            "User is not a participant of this chat".to_string(),
+        ));
+    }
+    let key = typing_key(room_id, user_id);
+    if !started {
+        // Already cleared by a previous stop or by the expiry
+        let cleared = redis_client
+            .del(&key)
+            .await
+            .map_err(MessageHandlerError::RedisError)?;
+        if cleared > 0 {
+            publish_typing(&redis_client, room_id, user_id, Records::TypingStop)
+                .await
+                .map_err(MessageHandlerError::RedisError)?;
+        }
+        return Ok(());
+    }
+    last_typing.insert(room_id, Instant::now());
+    // Each start gets its own token, so only the latest one is expired by its task
+    let token = uuid::Uuid::new_v4().to_string();
+    redis_client
+        .setex(&key, TYPING_TTL_SECONDS + 1, token.clone())
+        .await
+        .map_err(MessageHandlerError::RedisError)?;
+    publish_typing(&redis_client, room_id, user_id, Records::TypingStart)
+        .await
+        .map_err(MessageHandlerError::RedisError)?;
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(TYPING_TTL_SECONDS)).await;
+        let expired: usize = redis_client
+            .eval(
+                CallBuilder::script(EXPIRE_TYPING_SCRIPT)
+                    .keys(key)
+                    .args(token),
+            )
+            .await
+            .unwrap_or(0);
+        if expired > 0 {
+            publish_typing(&redis_client, room_id, user_id, Records::TypingStop)
+                .await
+                .ok();
+        }
+    });
+    Ok(())
+}
@@ -1,4 +1,8 @@
-use std::{collections::HashSet, net::SocketAddr};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    time::Instant,
+};
 
 use axum::{
     extract::{
@@ -45,6 +49,8 @@ pub async fn handle_websocket(
                 // Create a hashset to store queue strings
                 Ok(mut pubsub) => {
                     let mut subbed_channels: HashSet<String> = HashSet::new();
+                    // Last typing_start relayed per room, used to throttle this connection
+                    let mut last_typing: HashMap<i32, Instant> = HashMap::new();
                     // Add to pubsub
                     subbed_channels.insert(format!("priv_user:{}", user_id.to_string()));
 
@@ -70,8 +76,9 @@ pub async fn handle_websocket(
                                             &user_id,
                                             &mut pubsub,
                                             &mut ws_sender,
-                                            &state.prisma_client,
+                                            &state,
                                             &mut subbed_channels,
+                                            &mut last_typing,
                                         ).await;
                                         match handler {
                                             Ok(_) => {}
@@ -123,6 +130,11 @@ pub async fn handle_websocket(
                                                     message_to_send
                                                 }
                                             };
+                                        // Users don't need to see their own typing indicator
+                                        let is_typing = matches!(msg.record, crate::socket::interfaces::websocket_message::Records::TypingStart | crate::socket::interfaces::websocket_message::Records::TypingStop);
+                                        if is_typing && msg.data["user_id"] == serde_json::json!(user_id) {
+                                            continue;
+                                        }
                                         if channel == format!("priv_user:{}", user_id) {
                                             let handler = handle_private_pubsub_message(
                                                 &msg,
@@ -12,6 +12,8 @@ pub enum Records {
     RateLimit,
     ParticipantJoined,
     ParticipantLeft,
+    TypingStart,
+    TypingStop,
 }
 
 impl Serialize for Records {
@@ -26,6 +28,8 @@ impl Serialize for Records {
             Records::LeftQueue => serializer.serialize_str("msg_g2c_left_queue"),
             Records::ParticipantJoined => serializer.serialize_str("msg_g2c_participant_joined"),
             Records::ParticipantLeft => serializer.serialize_str("msg_g2c_participant_left"),
+            Records::TypingStart => serializer.serialize_str("msg_g2c_typing_start"),
+            Records::TypingStop => serializer.serialize_str("msg_g2c_typing_stop"),
         }
     }
 }
@@ -39,11 +43,15 @@ impl<'de> Deserialize<'de> for Records {
         match s.as_str() {
             "msg_c2g_subscribe_queue" => Ok(Records::JoinedQueue),
             "msg_c2g_unsubscribe_queue" => Ok(Records::LeftQueue),
+            "msg_c2g_typing_start" => Ok(Records::TypingStart),
+            "msg_c2g_typing_stop" => Ok(Records::TypingStop),
             "msg_g2c_send_message" => Ok(Records::Message),
             "msg_g2c_participant_joined" => Ok(Records::ParticipantJoined),
             "msg_g2c_participant_left" => Ok(Records::ParticipantLeft),
             "msg_g2c_joined_queue" => Ok(Records::JoinedQueue),
             "msg_g2c_left_queue" => Ok(Records::LeftQueue),
+            "msg_g2c_typing_start" => Ok(Records::TypingStart),
+            "msg_g2c_typing_stop" => Ok(Records::TypingStop),
 
             _ => Err(serde::de::Error::custom("expected a valid record")),
         }
@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use prisma_client_rust::{
+    operator::{and, or},
+    QueryError,
+};
+use serde::Serialize;
+
+use crate::{
+    chat::interfaces::single_user_param::SingleUserParam,
+    prisma_client::client::{user, user_blocks, PrismaClient},
+    rejection::path::CustomPathDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Serialize)]
+pub struct BlockUserErrorResponse {
+    pub success: bool,
+    pub http_code: u16,
+    pub error: String,
+}
+
+// True when either user blocked the other
+pub async fn is_blocked(
+    prisma_client: Arc<PrismaClient>,
+    user_id: i32,
+    other_id: i32,
+) -> Result<bool, QueryError> {
+    let block = prisma_client
+        .user_blocks()
+        .find_first(vec![or(vec![
+            and(vec![
+                user_blocks::blocker_id::equals(user_id),
+                user_blocks::blocked_id::equals(other_id),
+            ]),
+            and(vec![
+                user_blocks::blocker_id::equals(other_id),
+                user_blocks::blocked_id::equals(user_id),
+            ]),
+        ])])
+        .exec()
+        .await?;
+    Ok(block.is_some())
+}
+
+pub async fn block_user(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Path(SingleUserParam { user_id }), _): WithRejection<
+        Path<SingleUserParam>,
+        CustomPathDataRejection,
+    >,
+) -> Result<StatusCode, (StatusCode, Json<BlockUserErrorResponse>)> {
+    if user_id > i32::MAX as u32 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(BlockUserErrorResponse {
+                success: false,
+                http_code: 400,
+                error: "User id cannot exceed 32 bits signed".to_string(),
+            }),
+        ));
+    }
+    let user_id = user_id as i32;
+    if user_id == user.id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(BlockUserErrorResponse {
+                success: false,
+                http_code: 400,
+                error: "You can't block yourself".to_string(),
+            }),
+        ));
+    }
+    let target = state
+        .prisma_client
+        .user()
+        .find_unique(user::UniqueWhereParam::IdEquals(user_id))
+        .exec()
+        .await;
+    match target {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(BlockUserErrorResponse {
+                    success: false,
+                    http_code: 404,
+                    error: "User not found".to_string(),
+                }),
+            ))
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(BlockUserErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ))
+        }
+    };
+    let existing_block = state
+        .prisma_client
+        .user_blocks()
+        .find_first(vec![
+            user_blocks::blocker_id::equals(user.id),
+            user_blocks::blocked_id::equals(user_id),
+        ])
+        .exec()
+        .await;
+    match existing_block {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(BlockUserErrorResponse {
+                    success: false,
+                    http_code: 409,
+                    error: "User is already blocked".to_string(),
+                }),
+            ))
+        }
+        Err(_) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(BlockUserErrorResponse {
+                    success: false,
+                    http_code: 500,
+                    error: "Internal server error".to_string(),
+                }),
+            ))
+        }
+    };
+    let block = state
+        .prisma_client
+        .user_blocks()
+        .create(
+            user::UniqueWhereParam::IdEquals(user.id),
+            user::UniqueWhereParam::IdEquals(user_id),
+            vec![],
+        )
+        .exec()
+        .await;
+    match block {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(BlockUserErrorResponse {
+                success: false,
+                http_code: 500,
+                error: "Internal server error".to_string(),
+            }),
+        )),
+    }
+}
+
+pub async fn unblock_user(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Path(SingleUserParam { user_id }), _): WithRejection<
+        Path<SingleUserParam>,
+        CustomPathDataRejection,
+    >,
+) -> Result<StatusCode, (StatusCode, Json<BlockUserErrorResponse>)> {
+    if user_id > i32::MAX as u32 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(BlockUserErrorResponse {
+                success: false,
+                http_code: 400,
+                error: "User id cannot exceed 32 bits signed".to_string(),
+            }),
+        ));
+    }
+    let unblock = state
+        .prisma_client
+        .user_blocks()
+        .delete_many(vec![
+            user_blocks::blocker_id::equals(user.id),
+            user_blocks::blocked_id::equals(user_id as i32),
+        ])
+        .exec()
+        .await;
+    match unblock {
+        Ok(0) => Err((
+            StatusCode::NOT_FOUND,
+            Json(BlockUserErrorResponse {
+                success: false,
+                http_code: 404,
+                error: "User is not blocked".to_string(),
+            }),
+        )),
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(_) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(BlockUserErrorResponse {
+                success: false,
+                http_code: 500,
+                error: "Internal server error".to_string(),
+            }),
+        )),
+    }
+}
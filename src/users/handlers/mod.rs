@@ -1,2 +1,7 @@
+pub mod block_user;
 pub mod create_user;
 pub mod current_user;
+pub mod read_mentions;
+pub mod retrieve_blocks;
+pub mod retrieve_invites;
+pub mod retrieve_mentions;
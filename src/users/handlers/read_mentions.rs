@@ -0,0 +1,137 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use rustis::commands::GenericCommands;
+use serde::Serialize;
+
+use crate::{
+    chat::messages::read_messages::unread_mentions_key,
+    prisma_client::client::{mentions, user},
+    rejection::path::CustomPathDataRejection,
+    shared::arc_clients::State as AppState,
+    users::interfaces::mention_id_param::MentionIdParam,
+};
+
+#[derive(Serialize)]
+pub struct ReadMentionsResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+async fn mark_read(
+    state: AppState,
+    user_id: i32,
+    mut filters: Vec<mentions::WhereParam>,
+) -> (StatusCode, Json<ReadMentionsResponse>) {
+    filters.push(mentions::read::equals(false));
+    let update = state
+        .prisma_client
+        .mentions()
+        .update_many(
+            filters,
+            vec![
+                mentions::read::set(true),
+                mentions::read_at::set(Some(chrono::Utc::now().into())),
+            ],
+        )
+        .exec()
+        .await;
+    match update {
+        Ok(updated) => {
+            // The per room counters are rebuilt on the next unread lookup
+            if updated > 0 {
+                state
+                    .redis_client
+                    .del(unread_mentions_key(user_id))
+                    .await
+                    .ok();
+            }
+            (
+                StatusCode::OK,
+                Json(ReadMentionsResponse {
+                    success: true,
+                    http_code: 200,
+                    updated: Some(updated),
+                    error: None,
+                }),
+            )
+        }
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ReadMentionsResponse {
+                success: false,
+                http_code: 500,
+                updated: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        ),
+    }
+}
+
+pub async fn read_mention(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Path(MentionIdParam { mention_id }), _): WithRejection<
+        Path<MentionIdParam>,
+        CustomPathDataRejection,
+    >,
+) -> (StatusCode, Json<ReadMentionsResponse>) {
+    // Mentions of other users look the same as missing ones, reading one twice is fine
+    let mention = state
+        .prisma_client
+        .mentions()
+        .find_first(vec![
+            mentions::id::equals(mention_id),
+            mentions::user_id::equals(user.id),
+        ])
+        .exec()
+        .await;
+    match mention {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ReadMentionsResponse {
+                    success: false,
+                    http_code: 404,
+                    updated: None,
+                    error: Some("Mention not found".to_string()),
+                }),
+            )
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ReadMentionsResponse {
+                    success: false,
+                    http_code: 500,
+                    updated: None,
+                    error: Some("Internal server error".to_string()),
+                }),
+            )
+        }
+    };
+    mark_read(
+        state,
+        user.id,
+        vec![
+            mentions::id::equals(mention_id),
+            mentions::user_id::equals(user.id),
+        ],
+    )
+    .await
+}
+
+pub async fn read_all_mentions(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+) -> (StatusCode, Json<ReadMentionsResponse>) {
+    mark_read(state, user.id, vec![mentions::user_id::equals(user.id)]).await
+}
@@ -0,0 +1,72 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::Serialize;
+
+use crate::{
+    prisma_client::client::{user, user_blocks},
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Serialize)]
+pub struct BlockedUser {
+    pub id: i32,
+    pub username: String,
+    pub blocked_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize)]
+pub struct RetrieveBlocksResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<Vec<BlockedUser>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn retrieve_blocks(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+) -> (StatusCode, Json<RetrieveBlocksResponse>) {
+    let blocks = state
+        .prisma_client
+        .user_blocks()
+        .find_many(vec![user_blocks::blocker_id::equals(user.id)])
+        .order_by(user_blocks::created_at::order(
+            prisma_client_rust::Direction::Desc,
+        ))
+        .with(user_blocks::blocked::fetch())
+        .exec()
+        .await;
+    match blocks {
+        Ok(blocks) => (
+            StatusCode::OK,
+            Json(RetrieveBlocksResponse {
+                success: true,
+                http_code: 200,
+                blocks: Some(
+                    blocks
+                        .into_iter()
+                        .map(|block| {
+                            let blocked = block.blocked.unwrap();
+                            BlockedUser {
+                                id: blocked.id,
+                                username: blocked.username,
+                                blocked_at: block.created_at.into(),
+                            }
+                        })
+                        .collect(),
+                ),
+                error: None,
+            }),
+        ),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(RetrieveBlocksResponse {
+                success: false,
+                http_code: 500,
+                blocks: None,
+                error: Some("Internal server error".to_string()),
+            }),
+        ),
+    }
+}
@@ -0,0 +1,217 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::validation_error::ValidationError,
+    prisma_client::client::{invites, user, InviteState},
+    rejection::query::CustomQueryDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InviteDirection {
+    Incoming,
+    Outgoing,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct RetrieveInvitesQuery {
+    pub direction: Option<InviteDirection>,
+    pub state: Option<InviteState>,
+    #[validate(range(min = 1, message = "page must be greater than 0"))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 50, message = "limit must be between 1 and 50"))]
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct InboxUser {
+    pub id: i32,
+    pub username: String,
+}
+
+#[derive(Serialize)]
+pub struct InboxRoom {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Serialize)]
+pub struct InboxInvite {
+    pub id: String,
+    pub state: InviteState,
+    pub room: InboxRoom,
+    pub inviter: InboxUser,
+    pub invitee: InboxUser,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<invites::Data> for InboxInvite {
+    fn from(value: invites::Data) -> Self {
+        let room = value.room.unwrap();
+        let from = value.from.unwrap();
+        let to = value.user.unwrap();
+        Self {
+            id: value.id,
+            state: value.state,
+            room: InboxRoom {
+                id: room.id,
+                name: room.name,
+            },
+            inviter: InboxUser {
+                id: from.id,
+                username: from.username,
+            },
+            invitee: InboxUser {
+                id: to.id,
+                username: to.username,
+            },
+            created_at: value.created_at.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RetrieveInvitesResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invites: Option<Vec<InboxInvite>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub async fn retrieve_invites(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Query(query), _): WithRejection<
+        Query<RetrieveInvitesQuery>,
+        CustomQueryDataRejection,
+    >,
+) -> (StatusCode, Json<RetrieveInvitesResponse>) {
+    match query.validate() {
+        Ok(_) => {
+            let filters = match query.state {
+                Some(invite_state) => vec![invites::state::equals(invite_state)],
+                None => vec![],
+            };
+            let limit = query.limit.unwrap_or(25);
+            let skip = (query.page.unwrap_or(1) - 1).saturating_mul(limit);
+            let direction = query.direction.unwrap_or(InviteDirection::Incoming);
+            let relation: user::WithParam = match direction {
+                InviteDirection::Incoming => user::incoming_invites::fetch(filters)
+                    .order_by(invites::created_at::order(
+                        prisma_client_rust::Direction::Desc,
+                    ))
+                    .skip(skip)
+                    .take(limit)
+                    .with(invites::room::fetch())
+                    .with(invites::from::fetch())
+                    .with(invites::user::fetch())
+                    .into(),
+                InviteDirection::Outgoing => user::outgoing_invites::fetch(filters)
+                    .order_by(invites::created_at::order(
+                        prisma_client_rust::Direction::Desc,
+                    ))
+                    .skip(skip)
+                    .take(limit)
+                    .with(invites::room::fetch())
+                    .with(invites::from::fetch())
+                    .with(invites::user::fetch())
+                    .into(),
+            };
+            let user = state
+                .prisma_client
+                .user()
+                .find_unique(user::UniqueWhereParam::IdEquals(user.id))
+                .with(relation)
+                .exec()
+                .await;
+            let user = match user {
+                Ok(Some(user)) => user,
+                Ok(None) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(RetrieveInvitesResponse {
+                            success: false,
+                            http_code: 404,
+                            invites: None,
+                            validation_errors: None,
+                            error: Some("User not found".to_string()),
+                        }),
+                    )
+                }
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(RetrieveInvitesResponse {
+                            success: false,
+                            http_code: 500,
+                            invites: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let invites = match direction {
+                InviteDirection::Incoming => user.incoming_invites,
+                InviteDirection::Outgoing => user.outgoing_invites,
+            };
+            (
+                StatusCode::OK,
+                Json(RetrieveInvitesResponse {
+                    success: true,
+                    http_code: 200,
+                    invites: Some(
+                        invites
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(InboxInvite::from)
+                            .collect(),
+                    ),
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(RetrieveInvitesResponse {
+                    success: false,
+                    http_code: 422,
+                    invites: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
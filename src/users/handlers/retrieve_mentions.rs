@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use axum_extra::extract::WithRejection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    chat::messages::retrieve_messages::MessageReponse,
+    error::validation_error::ValidationError,
+    prisma_client::client::{mentions, messages, rooms, user, users_rooms},
+    rejection::query::CustomQueryDataRejection,
+    shared::arc_clients::State as AppState,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct RetrieveMentionsQuery {
+    // Only unread mentions when true, only read ones when false
+    pub unread: Option<bool>,
+    #[validate(range(min = 1, message = "page must be greater than 0"))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 50, message = "limit must be between 1 and 50"))]
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct Mention {
+    pub id: i32,
+    pub chat_id: i32,
+    pub read: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub message: MessageReponse,
+}
+
+impl From<mentions::Data> for Mention {
+    fn from(value: mentions::Data) -> Self {
+        Self {
+            id: value.id,
+            chat_id: value.room_id,
+            read: value.read,
+            created_at: value.created_at.into(),
+            message: (*value.message.unwrap()).into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RetrieveMentionsResponse {
+    pub success: bool,
+    pub http_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mentions: Option<Vec<Mention>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unread_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_errors: Option<Vec<ValidationError>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Mentions from rooms the user left, was kicked or banned from stay hidden
+pub fn visible_mentions(user_id: i32) -> Vec<mentions::WhereParam> {
+    vec![
+        mentions::user_id::equals(user_id),
+        mentions::room::is(vec![rooms::users_rooms::some(vec![
+            users_rooms::user_id::equals(user_id),
+        ])]),
+    ]
+}
+
+pub async fn retrieve_mentions(
+    State(state): State<AppState>,
+    Extension(user): Extension<user::Data>,
+    WithRejection(Query(query), _): WithRejection<
+        Query<RetrieveMentionsQuery>,
+        CustomQueryDataRejection,
+    >,
+) -> (StatusCode, Json<RetrieveMentionsResponse>) {
+    match query.validate() {
+        Ok(_) => {
+            let mut filters = visible_mentions(user.id);
+            if let Some(unread) = query.unread {
+                filters.push(mentions::read::equals(!unread));
+            }
+            let limit = query.limit.unwrap_or(25);
+            let mentions = state
+                .prisma_client
+                .mentions()
+                .find_many(filters)
+                .order_by(mentions::id::order(prisma_client_rust::Direction::Desc))
+                .skip((query.page.unwrap_or(1) - 1).saturating_mul(limit))
+                .take(limit)
+                .with(mentions::message::fetch().with(messages::user::fetch()))
+                .exec()
+                .await;
+            let mentions = match mentions {
+                Ok(mentions) => mentions,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(RetrieveMentionsResponse {
+                            success: false,
+                            http_code: 500,
+                            mentions: None,
+                            unread_count: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            let unread_count = state
+                .prisma_client
+                .mentions()
+                .count({
+                    let mut filters = visible_mentions(user.id);
+                    filters.push(mentions::read::equals(false));
+                    filters
+                })
+                .exec()
+                .await;
+            let unread_count = match unread_count {
+                Ok(unread_count) => unread_count,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(RetrieveMentionsResponse {
+                            success: false,
+                            http_code: 500,
+                            mentions: None,
+                            unread_count: None,
+                            validation_errors: None,
+                            error: Some("Internal server error".to_string()),
+                        }),
+                    )
+                }
+            };
+            (
+                StatusCode::OK,
+                Json(RetrieveMentionsResponse {
+                    success: true,
+                    http_code: 200,
+                    mentions: Some(mentions.into_iter().map(Mention::from).collect()),
+                    unread_count: Some(unread_count),
+                    validation_errors: None,
+                    error: None,
+                }),
+            )
+        }
+        Err(validation_errors) => {
+            let validation_errors =
+                validation_errors
+                    .field_errors()
+                    .into_iter()
+                    .map(|(field, errors)| ValidationError {
+                        field: field.to_string(),
+                        // Message is a cow
+                        messages: errors
+                            .iter()
+                            .map(|e| {
+                                e.message
+                                    .as_ref()
+                                    .map(|m| m.to_string())
+                                    .unwrap_or_else(|| "Unknown error".to_string())
+                            })
+                            .collect(),
+                    });
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(RetrieveMentionsResponse {
+                    success: false,
+                    http_code: 422,
+                    mentions: None,
+                    unread_count: None,
+                    validation_errors: Some(validation_errors.collect()),
+                    error: None,
+                }),
+            )
+        }
+    }
+}
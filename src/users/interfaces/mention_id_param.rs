@@ -0,0 +1,6 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct MentionIdParam {
+    pub mention_id: i32,
+}
@@ -0,0 +1 @@
+pub mod mention_id_param;
@@ -1,3 +1,4 @@
 pub mod handlers;
+pub mod interfaces;
 pub mod middlewares;
 pub mod users_router;
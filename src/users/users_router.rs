@@ -1,13 +1,21 @@
 use axum::{
     middleware::from_fn_with_state,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 
 use crate::shared::arc_clients::State;
 
 use super::{
-    handlers::{create_user::create_user, current_user::current_user},
+    handlers::{
+        block_user::{block_user, unblock_user},
+        create_user::create_user,
+        current_user::current_user,
+        read_mentions::{read_all_mentions, read_mention},
+        retrieve_blocks::retrieve_blocks,
+        retrieve_invites::retrieve_invites,
+        retrieve_mentions::retrieve_mentions,
+    },
     middlewares::is_authenticated::is_authed,
 };
 
@@ -15,6 +23,13 @@ pub fn users_router(state: State) -> Router {
     Router::new()
         .route("/create", post(create_user))
         .route("/", get(current_user))
+        .route("/invites", get(retrieve_invites))
+        .route("/mentions", get(retrieve_mentions))
+        .route("/mentions", put(read_all_mentions))
+        .route("/mentions/:mention_id", put(read_mention))
+        .route("/blocks", get(retrieve_blocks))
+        .route("/blocks/:user_id", put(block_user))
+        .route("/blocks/:user_id", delete(unblock_user))
         .layer(from_fn_with_state(state.clone(), is_authed))
         .with_state(state)
 }